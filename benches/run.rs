@@ -0,0 +1,71 @@
+//! Compares the throughput of running a program with and without history recording.
+//!
+//! Run with `cargo bench`.
+use rsc::emulator::{
+    config::{Config, History},
+    Assembler, Emulator,
+};
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 5;
+
+/// Counts down from 100000, long enough that the history outgrows its limit and steps are discarded.
+const SOURCE: &str = "
+LOOP:
+LDAC ONE
+MVAC
+LDAC COUNT
+SUB
+STAC COUNT
+JMPZ DONE
+JMP LOOP
+DONE:
+HALT
+ONE: 1
+COUNT: 186A0
+";
+
+/// Runs the program to completion 'RUNS' times, giving the total time taken and the micro-operations performed.
+///
+/// The emulator is built once and reset between runs, so only running the program is timed.
+fn run(instructions: &[u32], record: bool) -> (Duration, usize) {
+    let config = Config {
+        history: History {
+            record,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut emulator =
+        Emulator::with_config(instructions, config).expect("the benchmark program fits");
+
+    let mut elapsed = Duration::ZERO;
+    let mut steps = 0;
+    for _ in 0..RUNS {
+        emulator.reset();
+        let first_step = emulator.time_step();
+
+        let start = Instant::now();
+        while !emulator.halted() {
+            emulator
+                .cycle()
+                .expect("the benchmark program does not fault");
+        }
+        elapsed += start.elapsed();
+        steps += emulator.time_step() - first_step;
+    }
+
+    (elapsed, steps)
+}
+
+fn main() {
+    let assembler = Assembler::parse(SOURCE.to_string());
+
+    for (name, record) in [("recording", true), ("fast", false)] {
+        let (elapsed, steps) = run(&assembler.instructions, record);
+        println!(
+            "{name:>10}: {RUNS} runs in {elapsed:?}, {:.1} million micro-operations per second",
+            steps as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+}
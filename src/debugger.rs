@@ -1,56 +1,223 @@
-use crate::emulator::{util::Register, Emulator};
-use std::collections::HashSet;
+use crate::emulator::{
+    config::Config,
+    memory::LoadError,
+    util::{Fault, MicroState, Register, REGISTER_COUNT},
+    Emulator,
+};
+use progress::Stop;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 pub mod event;
 pub mod message;
+pub mod progress;
+pub mod provenance;
+pub mod snapshot;
 pub mod state;
 
+#[derive(Serialize, Deserialize)]
 pub struct Debugger {
     pub instructions_per_second: u32,
+    /// Stops execution once this many instructions have been executed.
+    pub instruction_budget: Option<u64>,
     breakpoints: HashSet<u32>,
+    micro_breakpoints: HashSet<MicroState>,
+    watchpoints: HashSet<u32>,
+    stop: Option<Stop>,
+    // Whether execution continues on each tick.
+    #[serde(skip)]
+    running: bool,
+    // The step each state of the registers was last seen at between instructions.
+    #[serde(skip)]
+    visits: HashMap<[u32; REGISTER_COUNT], usize>,
     pub emulator: Emulator,
 }
 
 impl Debugger {
-    pub fn new(instructions: &[u32]) -> Self {
-        Self {
+    pub fn new(instructions: &[u32]) -> Result<Self, LoadError> {
+        Debugger::with_config(instructions, Config::default())
+    }
+
+    /// Creates a debugger around an emulator for the machine described by the configuration, see
+    /// [`Emulator::with_config`].
+    pub fn with_config(instructions: &[u32], config: Config) -> Result<Self, LoadError> {
+        Ok(Self {
             instructions_per_second: 5,
-            emulator: Emulator::new(instructions),
+            instruction_budget: None,
+            emulator: Emulator::with_config(instructions, config)?,
             breakpoints: HashSet::new(),
-        }
+            micro_breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            stop: None,
+            running: false,
+            visits: HashMap::new(),
+        })
     }
 
     /// Steps over a breakpoint without disabling it.
     pub fn step_over(&mut self) {
-        if !self.halted() {
-            self.emulator.cycle();
+        if !self.halted() && !self.stopped() {
+            self.cycle();
         }
     }
 
     /// Steps forward through execution path by 'steps' amount at a time.
+    ///
+    /// An instruction is cut short when it arrives at a micro-operation breakpoint or writes to a watchpoint.
     pub fn stepi(&mut self, steps: usize) {
         for _ in 0..steps {
-            if !self.should_stop() {
-                self.emulator.cycle();
+            if self.should_stop() {
+                return;
+            }
+
+            loop {
+                self.micro_step();
+
+                if self.watch_hit(self.emulator.time_step()) {
+                    return;
+                }
+                if self.emulator.at_instruction_boundary() || self.should_stop() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Steps forward through execution path by 'steps' micro-operations at a time.
+    pub fn micro_stepi(&mut self, steps: usize) {
+        for _ in 0..steps {
+            if self.should_stop() {
+                return;
+            }
+
+            self.micro_step();
+            if self.watch_hit(self.emulator.time_step()) {
+                return;
             }
         }
     }
 
+    /// Steps over a breakpoint by a single micro-operation without disabling it.
+    pub fn micro_step_over(&mut self) {
+        if !self.halted() && !self.stopped() {
+            self.micro_step();
+        }
+    }
+
     /// Traces back execution path by 'steps' amount at a time.
+    ///
+    /// Each step lands on the start of an instruction, even when stopped partway through one.
     pub fn backi(&mut self, steps: usize) {
+        self.clear_stop();
+
         for _ in 0..steps {
-            self.emulator.registers.step_backward();
-            self.emulator.memory.step_backward();
+            while self.emulator.step_backward() {
+                if self.emulator.at_instruction_boundary() {
+                    break;
+                }
+            }
         }
     }
 
-    /// Traces back execution until we arrive back at the start.
-    pub fn restart(&mut self) {
+    /// Traces back execution path by 'steps' micro-operations at a time.
+    pub fn micro_backi(&mut self, steps: usize) {
+        self.clear_stop();
+
+        for _ in 0..steps {
+            self.emulator.step_backward();
+        }
+    }
+
+    /// Runs backward until the most recent breakpoint or watchpoint hit, or the oldest step held.
+    pub fn reverse_continue(&mut self) {
+        self.reverse_until(|_| false);
+    }
+
+    /// Runs backward until the condition holds, or a breakpoint or watchpoint is hit along the way.
+    ///
+    /// Stops where execution would have stopped running forward: before a breakpoint and just after the write to a
+    /// watched address. The step execution starts from is never considered a hit.
+    pub fn reverse_until(&mut self, mut condition: impl FnMut(&Emulator) -> bool) {
+        self.clear_stop();
+        let start = self.emulator.time_step();
+
         loop {
-            if !self.emulator.registers.step_backward() {
+            let time_step = self.emulator.time_step();
+            if time_step < start && self.watch_hit(time_step) {
                 return;
             }
-            self.emulator.memory.step_backward();
+
+            if !self.emulator.step_backward() {
+                return;
+            }
+
+            if self.should_stop() || condition(&self.emulator) {
+                return;
+            }
+        }
+    }
+
+    /// Traces back execution until we arrive back at the start.
+    pub fn restart(&mut self) {
+        self.clear_stop();
+        self.emulator.restart();
+    }
+
+    /// Returns the emulator to its power-on state, see [`Emulator::reset`].
+    pub fn reset(&mut self) {
+        self.clear_stop();
+        self.emulator.reset();
+    }
+
+    /// Continues after a HALT, from the instruction following it or from 'pc' when given. Indicates whether the
+    /// machine was halted, nothing is done otherwise.
+    pub fn resume(&mut self, pc: Option<u32>) -> bool {
+        let resumed = self.emulator.resume(pc);
+        if resumed {
+            self.clear_stop();
+        }
+        resumed
+    }
+
+    /// Replaces the program and resets, keeping breakpoints, watchpoints and attached devices, see
+    /// [`Emulator::load_program`].
+    pub fn load_program(
+        &mut self,
+        program: &[u32],
+        base: u32,
+        keep_memory: bool,
+    ) -> Result<(), LoadError> {
+        self.emulator.load_program(program, base, keep_memory)?;
+        self.clear_stop();
+        Ok(())
+    }
+
+    /// Moves to any recorded step in time, forward or backward.
+    pub fn seek(&mut self, time_step: usize) {
+        self.clear_stop();
+        self.emulator.seek(time_step);
+    }
+
+    /// Runs a single cycle, stopping on the fault if one occurs.
+    fn cycle(&mut self) {
+        match self.emulator.cycle() {
+            Ok(()) => self.check_progress(),
+            Err(fault) => {
+                log::warn!("{fault}");
+                self.stop = Some(Stop::Fault(fault));
+            }
+        }
+    }
+
+    /// Runs a single micro-operation, stopping on the fault if one occurs.
+    fn micro_step(&mut self) {
+        match self.emulator.micro_step() {
+            Ok(_) if self.emulator.at_instruction_boundary() => self.check_progress(),
+            Ok(_) => (),
+            Err(fault) => {
+                log::warn!("{fault}");
+                self.stop = Some(Stop::Fault(fault));
+            }
         }
     }
 
@@ -64,18 +231,86 @@ impl Debugger {
         self.breakpoints.remove(&address)
     }
 
+    /// Sets a breakpoint on a micro-operation, stopping whenever it is about to be performed.
+    ///
+    /// A microprogrammed control unit stops on the micro-instructions named after the state.
+    pub fn set_micro_breakpoint(&mut self, state: MicroState) {
+        self.micro_breakpoints.insert(state);
+    }
+
+    /// Removes a micro-operation breakpoint, returns if the removal acted on anything.
+    pub fn remove_micro_breakpoint(&mut self, state: MicroState) -> bool {
+        self.micro_breakpoints.remove(&state)
+    }
+
+    /// Watches an address, stopping execution just after any write to it.
+    pub fn set_watchpoint(&mut self, address: u32) {
+        self.watchpoints.insert(address);
+    }
+
+    /// Removes the watchpoint on an address, returns if the removal acted on anything.
+    pub fn remove_watchpoint(&mut self, address: u32) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    /// Indicates whether the step arriving at 'time_step' wrote to a watched address.
+    fn watch_hit(&self, time_step: usize) -> bool {
+        time_step > self.emulator.first_step()
+            && self
+                .emulator
+                .memory
+                .changes(time_step - 1)
+                .iter()
+                .any(|change| self.watchpoints.contains(&(change.0 as u32)))
+    }
+
     /// Indicates whether the underlying emulator is halted.
     pub fn halted(&mut self) -> bool {
         self.emulator.halted()
     }
 
+    /// Why the debugger stopped, when it was for a fault or because the program stopped making progress.
+    pub fn stop(&self) -> Option<Stop> {
+        self.stop
+    }
+
+    /// The fault the debugger is stopped on, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        match self.stop {
+            Some(Stop::Fault(fault)) => Some(fault),
+            _ => None,
+        }
+    }
+
+    /// Indicates whether execution is stopped, on a fault or because the program stopped making progress.
+    pub fn stopped(&self) -> bool {
+        self.stop.is_some()
+    }
+
     /// Returns if a given address is a breakpoint and is enabled.
     pub fn query(&mut self, address: u32) -> bool {
-        self.breakpoints.get(&address).is_some()
+        self.breakpoints.contains(&address)
     }
 
     /// Determines if the debugger should yield execution.
+    ///
+    /// Address breakpoints are only considered between instructions.
     pub fn should_stop(&mut self) -> bool {
-        self.halted() || self.query(self.emulator.registers.get(Register::PC))
+        self.halted()
+            || self.stopped()
+            || self.at_micro_breakpoint()
+            || (self.emulator.at_instruction_boundary()
+                && self.query(self.emulator.registers.get(Register::PC)))
+    }
+
+    /// Indicates whether the micro-operation, or micro-instruction, about to be performed has a breakpoint.
+    fn at_micro_breakpoint(&self) -> bool {
+        match self.emulator.micro_instruction() {
+            Some(instruction) => self
+                .micro_breakpoints
+                .iter()
+                .any(|state| state.as_str() == instruction.name),
+            None => self.micro_breakpoints.contains(&self.emulator.state()),
+        }
     }
 }
@@ -1,3 +1,5 @@
+use super::Debugger;
+
 #[derive(Debug, Clone, Copy)]
 pub enum Message {
     Run,
@@ -8,6 +10,79 @@ pub enum Message {
     StepOver,
     StepForward,
     StepBackward,
+    MicroStepForward,
+    MicroStepBackward,
+    Seek(usize),
+    ReverseContinue,
     SetBreakpoint(u32),
     RemoveBreakpoint(u32),
+    SetWatchpoint(u32),
+    RemoveWatchpoint(u32),
+}
+
+impl Debugger {
+    /// Acts on a message from the interface.
+    ///
+    /// While running, each tick executes an instruction until a breakpoint, watchpoint, HALT or fault stops it.
+    /// Any other movement through execution pauses.
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::Run => {
+                self.step_over();
+                self.running = !self.should_stop();
+            }
+            Message::Tick if self.running => {
+                self.stepi(1);
+                if self.should_stop() || self.watch_hit(self.emulator.time_step()) {
+                    self.running = false;
+                }
+            }
+            Message::Tick => (),
+            Message::Pause => self.running = false,
+            Message::Resume => {
+                self.running = false;
+                self.resume(None);
+            }
+            Message::Restart => {
+                self.running = false;
+                self.restart();
+            }
+            Message::StepOver => {
+                self.running = false;
+                self.step_over();
+            }
+            Message::StepForward => {
+                self.running = false;
+                self.stepi(1);
+            }
+            Message::StepBackward => {
+                self.running = false;
+                self.backi(1);
+            }
+            Message::MicroStepForward => {
+                self.running = false;
+                self.micro_stepi(1);
+            }
+            Message::MicroStepBackward => {
+                self.running = false;
+                self.micro_backi(1);
+            }
+            Message::Seek(time_step) => {
+                self.running = false;
+                self.seek(time_step);
+            }
+            Message::ReverseContinue => {
+                self.running = false;
+                self.reverse_continue();
+            }
+            Message::SetBreakpoint(address) => self.set_breakpoint(address),
+            Message::RemoveBreakpoint(address) => {
+                self.remove_breakpoint(address);
+            }
+            Message::SetWatchpoint(address) => self.set_watchpoint(address),
+            Message::RemoveWatchpoint(address) => {
+                self.remove_watchpoint(address);
+            }
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use super::Debugger;
+use crate::emulator::util::Fault;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The most machine states remembered for detecting loops, older ones are forgotten past this.
+const MAX_VISITS: usize = 1 << 16;
+
+/// Why the debugger stopped running, besides breakpoints, watchpoints and HALT.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stop {
+    #[error(transparent)]
+    Fault(#[from] Fault),
+    #[error("The program is not making progress, it is looping between {0:#x} and {1:#x}")]
+    NoProgress(u32, u32),
+    #[error("The budget of {0} instructions has been used up")]
+    BudgetExhausted(u64),
+}
+
+impl Debugger {
+    /// Stops on a program that has run out of budget, or has returned to an earlier state with nothing changed
+    /// since and so will loop forever. Called between instructions.
+    ///
+    /// Loops are only detected while history is being recorded.
+    pub(super) fn check_progress(&mut self) {
+        if let Some(budget) = self.instruction_budget {
+            if self.emulator.profile().instructions() >= budget {
+                self.stop = Some(Stop::BudgetExhausted(budget));
+                return;
+            }
+        }
+
+        if !self.emulator.recording() {
+            return;
+        }
+
+        if self.visits.len() >= MAX_VISITS {
+            self.visits.clear();
+        }
+
+        let time_step = self.emulator.time_step();
+        let Some(visited) = self
+            .visits
+            .insert(self.emulator.registers.values(), time_step)
+        else {
+            return;
+        };
+
+        if visited < self.emulator.first_step() || !self.emulator.memory.unchanged_since(visited) {
+            return;
+        }
+
+        // Every instruction of the loop was executed since the earlier visit.
+        let (start, end) = (visited..time_step)
+            .filter_map(|step| self.emulator.instruction_address_at(step))
+            .fold((u32::MAX, 0), |(start, end), address| {
+                (start.min(address), end.max(address))
+            });
+        self.stop = Some(Stop::NoProgress(start, end));
+    }
+
+    /// Clears the stop and forgets the states seen, which may no longer be part of the timeline after moving
+    /// through it.
+    pub(super) fn clear_stop(&mut self) {
+        self.stop = None;
+        self.visits.clear();
+    }
+}
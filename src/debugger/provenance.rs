@@ -0,0 +1,73 @@
+use super::Debugger;
+use crate::emulator::{isa::Instruction, memory::Change, util::Register};
+
+/// A register or memory address whose writes can be traced through the timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Register(Register),
+    Address(u32),
+}
+
+/// A single write to a location, found in the recorded history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Write {
+    /// The step in time the write was performed at, seeking here lands just before it.
+    pub time_step: usize,
+    /// The address of the instruction that performed the write.
+    pub pc: u32,
+    /// The instruction that performed the write, unless its opcode was illegal.
+    pub instruction: Option<Instruction>,
+    pub previous: u32,
+    pub value: u32,
+}
+
+impl Debugger {
+    /// The most recent write to a location before the current step.
+    pub fn last_write(&self, location: Location) -> Option<Write> {
+        self.writes(location).next()
+    }
+
+    /// Every write to a location before the current step that is still held, most recent first.
+    pub fn writes(&self, location: Location) -> impl Iterator<Item = Write> + '_ {
+        let emulator = &self.emulator;
+
+        (emulator.first_step()..emulator.time_step())
+            .rev()
+            .filter_map(move |time_step| {
+                let (changes, idx) = match location {
+                    Location::Register(reg) => {
+                        (emulator.registers.changes(time_step), reg as usize)
+                    }
+                    Location::Address(address) => {
+                        (emulator.memory.changes(time_step), address as usize)
+                    }
+                };
+
+                // A location can be written more than once in a step, the first change holds the value before it.
+                let first = changes.iter().find(|change| change.0 == idx)?;
+                let Change(_, _, value) = changes.iter().rev().find(|change| change.0 == idx)?;
+                let pc = emulator.instruction_address_at(time_step)?;
+
+                Some(Write {
+                    time_step,
+                    pc,
+                    instruction: emulator
+                        .value_at(pc, time_step)
+                        .and_then(|opcode| emulator.isa().decode(opcode)),
+                    previous: first.1,
+                    value: *value,
+                })
+            })
+    }
+
+    /// Moves to just before the most recent write to a location, indicating if there was one.
+    pub fn jump_to_last_write(&mut self, location: Location) -> bool {
+        match self.last_write(location) {
+            Some(write) => {
+                self.seek(write.time_step);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use super::Debugger;
+use crate::emulator::{isa::Isa, microcode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The version of the snapshot format written by this build, bumped whenever the format changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("The snapshot could not be read: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("The snapshot is version {0}, but only version {SNAPSHOT_VERSION} is supported")]
+    UnsupportedVersion(u32),
+    #[error("The snapshot is inconsistent, its {0} do not match the rest of it")]
+    Inconsistent(&'static str),
+    #[error("The snapshot uses {0} with opcode {1:#x}, which is not in the instruction set given")]
+    UnknownInstruction(String, u32),
+    #[error("The snapshot's microcode does not fit the instruction set given: {0}")]
+    Microcode(#[from] microcode::Error),
+}
+
+/// Only the version is read first, so that a snapshot from another version is reported as such.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    source: &'a str,
+    // The mnemonic and opcode of every instruction the program was assembled with.
+    isa: Vec<(&'a str, u32)>,
+    debugger: &'a Debugger,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    source: String,
+    isa: Vec<(String, u32)>,
+    debugger: Debugger,
+}
+
+impl Debugger {
+    /// Saves the complete state of the debugger, its emulator and history, along with the source it was assembled from.
+    pub fn save(&self, source: &str) -> Result<String, Error> {
+        Ok(serde_json::to_string(&SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            source,
+            isa: self
+                .emulator
+                .isa()
+                .iter()
+                .map(|instruction| (instruction.mnemonic, instruction.opcode))
+                .collect(),
+            debugger: self,
+        })?)
+    }
+
+    /// Restores a debugger saved by [`Debugger::save`] with the instructions of the textbook RSC and its extensions,
+    /// returning it along with its source.
+    ///
+    /// Devices are not saved and must be attached again.
+    pub fn load(data: &str) -> Result<(Debugger, String), Error> {
+        Debugger::load_with(data, &Isa::rsc())
+    }
+
+    /// Restores a debugger saved by [`Debugger::save`], taking the instructions it was saved with from 'isa'.
+    ///
+    /// The source should be assembled again at the base and width of the emulator with its instruction set.
+    pub fn load_with(data: &str, isa: &Isa) -> Result<(Debugger, String), Error> {
+        let Header { version } = serde_json::from_str(data)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let Snapshot {
+            source,
+            isa: saved,
+            mut debugger,
+        } = serde_json::from_str(data)?;
+        debugger.emulator.validate().map_err(Error::Inconsistent)?;
+
+        let isa = saved
+            .into_iter()
+            .map(|(mnemonic, opcode)| {
+                isa.lookup(&mnemonic)
+                    .filter(|instruction| instruction.opcode == opcode)
+                    .ok_or(Error::UnknownInstruction(mnemonic, opcode))
+            })
+            .collect::<Result<Isa, _>>()?;
+        debugger.emulator.set_isa(isa)?;
+
+        Ok((debugger, source))
+    }
+}
@@ -1,6 +1,24 @@
+use super::{progress::Stop, Debugger};
+
 #[derive(Debug, Clone, Copy)]
 pub enum State {
     Running,
     Paused,
     Halted,
+    Stopped(Stop),
+}
+
+impl Debugger {
+    /// The state execution is in, a stop taking precedence over a HALT.
+    pub fn state(&mut self) -> State {
+        if let Some(stop) = self.stop {
+            State::Stopped(stop)
+        } else if self.halted() {
+            State::Halted
+        } else if self.running {
+            State::Running
+        } else {
+            State::Paused
+        }
+    }
 }
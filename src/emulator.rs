@@ -1,186 +1,1095 @@
 pub mod assembler;
+pub mod config;
+pub mod datapath;
+pub mod device;
+pub mod history;
+pub mod isa;
 pub mod memory;
+pub mod microcode;
+pub mod observer;
+pub mod profile;
 pub mod util;
 
 pub use assembler::Assembler;
-use memory::{Memory, Registers};
-use util::{Instruction, Register};
+use config::{Config, Guard, History, Reset, WordWidth};
+use datapath::{ControlSignal, Datapath};
+use history::Checkpoint;
+use isa::{Context, Instruction, Isa, Operation};
+use memory::{Change, LoadError, Memory, Registers};
+use microcode::{Microcode, Next, Signal, FETCH_ADDRESS, MICRO_STEP_LIMIT};
+use observer::{Notification, Observer, ObserverId};
+use profile::{Event, Profile};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use util::{Fault, MicroState, Register, WordKind};
 
+use MicroState as M;
 use Register as R;
 
+/// The control state of the machine at a step in time, recorded so that it can be returned to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Control {
+    state: MicroState,
+    micro_pc: usize,
+    instruction_address: u32,
+    cycles: u64,
+    // What the datapath did during the step arriving at this one.
+    datapath: Datapath,
+}
+
+/// A value written through OUT along with the step in time it was written at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Output {
+    pub time_step: usize,
+    pub value: u32,
+}
+
+/// A likely bug let through by a guard set to warn, along with the step in time it was found at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub time_step: usize,
+    pub fault: Fault,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Emulator {
     pub registers: Registers,
     pub memory: Memory,
+    width: WordWidth,
+    // The micro-operation to be performed next by the hardwired control unit.
+    state: MicroState,
+    // The micro-instruction to be performed next by the microprogrammed control unit.
+    micro_pc: usize,
+    // Address of the instruction currently being executed, reported in faults.
+    instruction_address: u32,
+    // Clock cycles taken, one for every micro-operation performed.
+    cycles: u64,
+    datapath: Datapath,
+    output: Vec<Output>,
+    warnings: Vec<Warning>,
+    profile: Profile,
+    code_guard: Guard,
+    // The step each word was first read at as part of the instruction under way, its opcode or an operand, for
+    // catching stores over code which has been executed.
+    executed: BTreeMap<u32, usize>,
+    uninitialized_guard: Guard,
+    interrupt_vector: Option<u32>,
+    extended_flags: bool,
+    // The program as it was loaded, for loading it again on reset.
+    program: Vec<u32>,
+    reset: Reset,
+    // Whether each word of the program is code or data, empty until classified.
+    layout: Vec<WordKind>,
+    history: History,
+    time_step: usize,
+    // The oldest step in time that is still held, the first step in the trace.
+    first_step: usize,
+    // The control state at every step held, including those after the current step that can be redone.
+    trace: Vec<Control>,
+    checkpoints: VecDeque<Checkpoint>,
+    microcode: Option<Microcode>,
+    #[serde(skip)]
+    isa: Isa,
+    #[serde(skip)]
+    observers: Vec<(ObserverId, Box<dyn Observer>)>,
+    // The id given to the next observer added.
+    #[serde(skip)]
+    next_observer: usize,
+    // Notifications for the micro-operation being performed, sent once it completes.
+    #[serde(skip)]
+    notifications: Vec<Notification>,
+    // Notifications held back until the instruction being cycled through completes, dropped should it fault.
+    #[serde(skip)]
+    held: Option<Vec<Notification>>,
 }
 
 impl Emulator {
-    pub fn new(memory: &[u32]) -> Self {
-        Emulator {
-            registers: Registers::new(),
-            memory: Memory::new(memory),
+    pub fn new(memory: &[u32]) -> Result<Self, LoadError> {
+        Emulator::with_config(memory, Config::default())
+    }
+
+    /// Creates an emulator with an address space of 'size' words and the program loaded at 'base'.
+    ///
+    /// Execution begins at the base of the program.
+    pub fn with_layout(program: &[u32], base: u32, size: usize) -> Result<Self, LoadError> {
+        Emulator::with_config(
+            program,
+            Config {
+                base,
+                memory_size: size,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Creates an emulator for the machine described by the configuration, provided the program fits in its
+    /// address space.
+    pub fn with_config(program: &[u32], config: Config) -> Result<Self, LoadError> {
+        let mut emulator = Emulator {
+            registers: Registers::with_width(config.width),
+            memory: Memory::with_layout(program, config.base, config.memory_size, config.width)?,
+            width: config.width,
+            state: M::FETCH1,
+            micro_pc: FETCH_ADDRESS,
+            instruction_address: config.base,
+            cycles: 0,
+            datapath: Datapath::default(),
+            output: Vec::new(),
+            warnings: Vec::new(),
+            profile: Profile::default(),
+            code_guard: config.code_guard,
+            executed: BTreeMap::new(),
+            uninitialized_guard: config.uninitialized_guard,
+            interrupt_vector: config.interrupt_vector,
+            extended_flags: config.extended_flags,
+            program: program.to_vec(),
+            reset: config.reset,
+            layout: Vec::new(),
+            history: config.history,
+            time_step: 0,
+            first_step: 0,
+            trace: Vec::new(),
+            checkpoints: VecDeque::new(),
+            microcode: config
+                .microprogrammed
+                .then(|| Microcode::for_isa(&Isa::default(), config.width)),
+            isa: Isa::default(),
+            observers: Vec::new(),
+            next_observer: 0,
+            notifications: Vec::new(),
+            held: None,
+        };
+
+        emulator.reset();
+        if !config.history.record {
+            emulator.set_recording(false);
+        }
+        Ok(emulator)
+    }
+
+    /// Returns to the power-on state, loading the program again and giving registers their reset values.
+    ///
+    /// Every step, output, warning and profile count is forgotten. Attached devices return to the state they were
+    /// created with.
+    pub fn reset(&mut self) {
+        self.memory.reset_devices();
+        self.memory.reset(&self.program);
+        self.start();
+    }
+
+    /// Gives registers their reset values and forgets every step, once the program is loaded.
+    fn start(&mut self) {
+        self.registers.reset(self.reset.values(self.memory.base()));
+
+        self.state = M::FETCH1;
+        self.micro_pc = FETCH_ADDRESS;
+        self.instruction_address = self.registers.get(R::PC);
+        self.cycles = 0;
+        self.datapath = Datapath::default();
+        self.output.clear();
+        self.warnings.clear();
+        self.profile = Profile::default();
+        self.executed.clear();
+        self.time_step = 0;
+        self.first_step = 0;
+        self.trace = vec![self.control()];
+        self.checkpoints.clear();
+        if self.history.record {
+            self.checkpoint();
         }
     }
 
+    /// Replaces the program, loading it at 'base', and resets the registers, see [`Emulator::reset`]. The program
+    /// must be classified again to be guarded against executing its data.
+    ///
+    /// Attached devices keep their state, and the rest of memory too when 'keep_memory' is set, so that programs
+    /// can be run one after another on the same machine, calling into the code of those before. Nothing is changed
+    /// when the program does not fit.
+    pub fn load_program(
+        &mut self,
+        program: &[u32],
+        base: u32,
+        keep_memory: bool,
+    ) -> Result<(), LoadError> {
+        self.memory.load(program, base, keep_memory)?;
+        self.program = program.to_vec();
+        self.layout.clear();
+        self.start();
+        Ok(())
+    }
+
+    /// The state registers power on and reset to.
+    pub fn reset_config(&self) -> Reset {
+        self.reset
+    }
+
+    /// Changes the state registers reset to, taking effect on the next [`Emulator::reset`].
+    pub fn set_reset_config(&mut self, reset: Reset) {
+        self.reset = reset;
+    }
+
+    /// The word width the emulator was configured with.
+    pub fn width(&self) -> WordWidth {
+        self.width
+    }
+
     pub fn halted(&self) -> bool {
         self.registers.get(R::S) == 1
     }
 
-    /// One entire cycle of execution.
-    pub fn cycle(&mut self) {
-        let instruction = self.fetch();
-        self.execute(instruction);
+    /// The micro-operation that will be performed on the next step by the hardwired control unit.
+    ///
+    /// A microprogrammed control unit steps through micro-instructions instead, see [`Emulator::micro_pc`].
+    pub fn state(&self) -> MicroState {
+        self.state
+    }
+
+    /// The address of the instruction being executed, or the last one executed when between instructions.
+    pub fn instruction_address(&self) -> u32 {
+        self.instruction_address
+    }
+
+    /// Indicates whether the emulator is between instructions, which includes being about to enter an interrupt.
+    pub fn at_instruction_boundary(&self) -> bool {
+        match &self.microcode {
+            Some(microcode) => {
+                self.micro_pc == FETCH_ADDRESS || Some(self.micro_pc) == microcode.interrupt
+            }
+            None => matches!(self.state, M::FETCH1 | M::INT1),
+        }
+    }
+
+    /// The number of micro-operations performed so far, which is the current step in time.
+    pub fn time_step(&self) -> usize {
+        self.time_step
+    }
+
+    /// The furthest step in time that has been recorded, which can be returned to after stepping backward.
+    pub fn last_step(&self) -> usize {
+        self.first_step + self.trace.len() - 1
+    }
+
+    /// The clock cycles taken so far, one for every micro-operation or micro-instruction performed.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// The control signals asserted, and the values on the bus and out of the ALU, during the step arriving at the
+    /// current one.
+    pub fn datapath(&self) -> Datapath {
+        self.datapath
+    }
+
+    /// Every value written through OUT before the current step, oldest first.
+    pub fn output(&self) -> &[Output] {
+        let written = self
+            .output
+            .partition_point(|o| o.time_step < self.time_step);
+        &self.output[..written]
+    }
+
+    /// Every warning raised before the current step, oldest first.
+    pub fn warnings(&self) -> &[Warning] {
+        let raised = self
+            .warnings
+            .partition_point(|w| w.time_step < self.time_step);
+        &self.warnings[..raised]
+    }
+
+    /// Per-address counts of the instructions executed and the words read and written before the current step.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Classifies each word of the program as code or data, as the assembler laid it out.
+    pub fn classify(&mut self, layout: Vec<WordKind>) {
+        self.layout = layout;
+    }
+
+    /// The instructions the emulator decodes.
+    pub fn isa(&self) -> &Isa {
+        &self.isa
+    }
+
+    /// Replaces the instructions the emulator decodes, which should be those the program was assembled with.
+    ///
+    /// A microprogrammed control unit has its mapping ROM rebuilt for the new opcodes, nothing is replaced when its
+    /// microcode lacks the micro-routine of an instruction. The instruction set is not part of a snapshot and must
+    /// be set again after one is restored.
+    pub fn set_isa(&mut self, isa: Isa) -> Result<(), microcode::Error> {
+        if let Some(microcode) = &mut self.microcode {
+            microcode.map(&isa)?;
+        }
+        self.isa = isa;
+        Ok(())
+    }
+
+    /// Adds an observer to be told about everything the emulator does from now on.
+    ///
+    /// Observers are not part of a snapshot and must be added again after one is restored. The id returned removes
+    /// it again.
+    pub fn observe(&mut self, observer: Box<dyn Observer>) -> ObserverId {
+        let id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+        self.observers.push((id, observer));
+        id
+    }
+
+    /// Removes an observer, handing it back. Nothing is removed when it already was.
+    pub fn unobserve(&mut self, id: ObserverId) -> Option<Box<dyn Observer>> {
+        let idx = self
+            .observers
+            .iter()
+            .position(|(observer, _)| *observer == id)?;
+        Some(self.observers.remove(idx).1)
+    }
+
+    /// One entire cycle of execution, finishing the current instruction if it was started by micro-stepping.
+    ///
+    /// A faulting instruction has its partial changes undone, leaving the machine as it was before the cycle. So does
+    /// one whose micro-routine runs past [`MICRO_STEP_LIMIT`] micro-instructions without ending. Observers are told
+    /// about the instruction once it completes, and nothing of one that is undone.
+    pub fn cycle(&mut self) -> Result<(), Fault> {
+        let start = self.time_step();
+        if !self.observers.is_empty() {
+            self.held = Some(Vec::new());
+        }
+
+        let fault = loop {
+            if let Err(fault) = self.micro_step() {
+                break fault;
+            }
+
+            if self.at_instruction_boundary() {
+                if let Some(held) = self.held.take() {
+                    self.send(&held);
+                }
+                return Ok(());
+            }
+            if self.time_step() - start >= MICRO_STEP_LIMIT {
+                break Fault::MicroStepLimit(self.instruction_address);
+            }
+        };
+        self.held = None;
+        while self.time_step() > start && self.step_backward() {}
+        self.truncate_future();
+        Err(fault)
+    }
+
+    /// Performs a single micro-operation, or micro-instruction when the control unit is microprogrammed, returning
+    /// the hardwired state that was performed.
+    ///
+    /// A faulting micro-operation has its partial changes undone.
+    /// Performing a micro-operation after stepping backward forgets the steps that could have been redone.
+    pub fn micro_step(&mut self) -> Result<MicroState, Fault> {
+        if self.time_step < self.last_step() {
+            self.truncate_future();
+        }
+
+        let start = self.control();
+        let warnings = self.warnings.len();
+        let event = self.event();
+        self.datapath = Datapath::default();
+        let performed = match self.microcode {
+            Some(_) => self.perform_micro_instruction(),
+            None => self.perform(start.state).map(|next| self.state = next),
+        };
+        if let Err(fault) = performed {
+            self.notifications.clear();
+            self.registers.rollback();
+            self.memory.rollback();
+            self.warnings.truncate(warnings);
+            self.set_control(start);
+            return Err(fault);
+        }
+
+        self.memory.tick();
+        self.cycles += 1;
+        if let Some(event) = event {
+            self.profile.count(event);
+        }
+        self.end_step();
+        Ok(start.state)
+    }
+
+    /// Clears S after a HALT so that execution continues, from the instruction following it or from 'pc' when given.
+    ///
+    /// Resuming takes a step of its own, which can be stepped back like any other. Indicates whether the machine was halted,
+    /// nothing is done otherwise.
+    pub fn resume(&mut self, pc: Option<u32>) -> bool {
+        if !self.halted() {
+            return false;
+        }
+
+        if self.time_step < self.last_step() {
+            self.truncate_future();
+        }
+
+        self.datapath = Datapath::default();
+        self.set(R::S, 0);
+        if let Some(pc) = pc {
+            self.set(R::PC, pc);
+        }
+        self.end_step();
+        true
+    }
+
+    /// Notifies observers of everything done in the current step, unless held back by [`Emulator::cycle`], and moves
+    /// on to the next one.
+    fn end_step(&mut self) {
+        if !self.observers.is_empty() {
+            for Change(reg, _, val) in self.registers.changes(self.time_step) {
+                let reg = Register::from_index(*reg).expect("changes are to valid registers");
+                self.notifications
+                    .push(Notification::RegisterSet(reg, *val));
+            }
+
+            let notifications = std::mem::take(&mut self.notifications);
+            match &mut self.held {
+                Some(held) => held.extend(notifications),
+                None => self.send(&notifications),
+            }
+        }
+        self.notifications.clear();
 
         // Timeless engine steps forward one step in execution
         self.registers.step_forward();
         self.memory.step_forward();
+        self.time_step += 1;
+        self.trace.push(self.control());
+        if self.history.record {
+            self.record_history();
+        } else if self.at_instruction_boundary() {
+            // Only the instruction under way is held, for undoing it should it fault.
+            self.discard_before(self.time_step);
+        }
+    }
+
+    /// Undoes the last micro-operation, indicating if there was one to undo.
+    pub fn step_backward(&mut self) -> bool {
+        if self.time_step == self.first_step {
+            return false;
+        }
+
+        self.registers.step_backward();
+        self.memory.step_backward();
+        self.time_step -= 1;
+        self.set_control(self.trace[self.time_step - self.first_step]);
+
+        if let Some(event) = self.event() {
+            self.profile.uncount(event);
+        }
+        true
+    }
+
+    /// Redoes the next recorded micro-operation after stepping backward, indicating if there was one to redo.
+    pub fn redo(&mut self) -> bool {
+        if self.time_step == self.last_step() {
+            return false;
+        }
+
+        if let Some(event) = self.event() {
+            self.profile.count(event);
+        }
+        self.registers.redo();
+        self.memory.redo();
+        self.time_step += 1;
+        self.set_control(self.trace[self.time_step - self.first_step]);
+        true
+    }
+
+    /// Forgets every step recorded after the current one.
+    fn truncate_future(&mut self) {
+        self.registers.truncate_future();
+        self.memory.truncate_future();
+        self.trace.truncate(self.time_step - self.first_step + 1);
+
+        let time_step = self.time_step;
+        self.output.retain(|o| o.time_step < time_step);
+        self.warnings.retain(|w| w.time_step < time_step);
+        self.executed.retain(|_, step| *step < time_step);
+        while self
+            .checkpoints
+            .back()
+            .is_some_and(|checkpoint| checkpoint.time_step() > time_step)
+        {
+            self.checkpoints.pop_back();
+        }
+    }
+
+    fn control(&self) -> Control {
+        Control {
+            state: self.state,
+            micro_pc: self.micro_pc,
+            instruction_address: self.instruction_address,
+            cycles: self.cycles,
+            datapath: self.datapath,
+        }
+    }
+
+    fn set_control(&mut self, control: Control) {
+        self.state = control.state;
+        self.micro_pc = control.micro_pc;
+        self.instruction_address = control.instruction_address;
+        self.cycles = control.cycles;
+        self.datapath = control.datapath;
+    }
+
+    /// What the micro-operation about to be performed counts towards in the profile.
+    fn event(&self) -> Option<Event> {
+        if self.microcode.is_some() {
+            return self.micro_event();
+        }
+
+        let address = self.registers.get(R::AR);
+
+        match self.state {
+            M::FETCH3 => {
+                let instruction = self.isa.decode(self.registers.get(R::DR))?;
+                let taken = instruction
+                    .operation
+                    .condition()
+                    .map(|flag| self.registers.get(flag) == 1);
+                Some(Event::Executed(self.instruction_address, taken))
+            }
+            M::FETCH2
+            | M::LDAC1
+            | M::LDAC2
+            | M::LDAC4
+            | M::STAC1
+            | M::STAC2
+            | M::JMP1
+            | M::JMP2
+            | M::JMPZY1
+            | M::JMPZY2
+            | M::IMM1
+            | M::IMM2 => Some(Event::Read(address)),
+            M::STAC5 => Some(Event::Written(address)),
+            _ => None,
+        }
+    }
+
+    /// What the micro-instruction about to be performed counts towards in the profile.
+    ///
+    /// Memory is accessed at the address held in AR before the micro-instruction.
+    fn micro_event(&self) -> Option<Event> {
+        let instruction = self.micro_instruction()?;
+        let address = self.registers.get(R::AR);
+
+        if instruction.next == Next::Map {
+            let opcode = if instruction.signals.contains(&Signal::DrToIr) {
+                self.registers.get(R::DR)
+            } else {
+                self.registers.get(R::IR)
+            };
+            let taken = self
+                .isa
+                .decode(opcode)?
+                .operation
+                .condition()
+                .map(|flag| self.registers.get(flag) == 1);
+            Some(Event::Executed(self.instruction_address, taken))
+        } else if instruction.signals.contains(&Signal::Write) {
+            Some(Event::Written(address))
+        } else if instruction.signals.contains(&Signal::Read) {
+            Some(Event::Read(address))
+        } else {
+            None
+        }
+    }
+
+    /// Performs the register transfers of the given state and determines the state which follows it.
+    fn perform(&mut self, state: MicroState) -> Result<MicroState, Fault> {
+        let next = match state {
+            M::FETCH1 => {
+                self.begin_instruction()?;
+                self.transfer(R::PC, R::AR);
+                M::FETCH2
+            }
+            M::FETCH2 => {
+                self.load_dr()?;
+                self.inc_pc();
+                M::FETCH3
+            }
+            M::FETCH3 => {
+                let opcode = self.registers.get(R::DR);
+                let instruction = self
+                    .isa
+                    .decode(opcode)
+                    .filter(|i| self.enabled(i))
+                    .ok_or(Fault::IllegalOpcode(self.instruction_address, opcode))?;
+
+                self.transfer(R::DR, R::IR);
+                self.transfer(R::PC, R::AR);
+                let address = self.instruction_address;
+                self.notify(Notification::Fetch(address, instruction));
+                self.decode(instruction)
+            }
+            M::LDAC1 => self.read_operand(true, M::LDAC2, M::LDAC3)?,
+            M::STAC1 => self.read_operand(true, M::STAC2, M::STAC3)?,
+            M::LDAC2 => self.read_operand_high(true, M::LDAC3)?,
+            M::STAC2 => self.read_operand_high(true, M::STAC3)?,
+            M::LDAC3 => {
+                self.operand_to(R::AR);
+                M::LDAC4
+            }
+            M::LDAC4 => {
+                self.load_dr()?;
+                M::LDAC5
+            }
+            M::LDAC5 => self.finish(|e| e.transfer(R::DR, R::ACC)),
+            M::STAC3 => {
+                self.operand_to(R::AR);
+                M::STAC4
+            }
+            M::STAC4 => {
+                self.transfer(R::ACC, R::DR);
+                M::STAC5
+            }
+            M::STAC5 => {
+                self.stac()?;
+                self.finish(|_| ())
+            }
+            M::JMP1 => self.read_operand(false, M::JMP2, M::JMP3)?,
+            M::JMPZY1 => self.read_operand(false, M::JMPZY2, M::JMPZY3)?,
+            M::JMP2 => self.read_operand_high(false, M::JMP3)?,
+            M::JMPZY2 => self.read_operand_high(false, M::JMPZY3)?,
+            M::JMP3 | M::JMPZY3 => self.finish(|e| e.operand_to(R::PC)),
+            M::JMPZN1 if self.textbook() => {
+                self.inc_pc();
+                M::JMPZN2
+            }
+            M::JMPZN1 | M::JMPZN2 => self.finish(Self::inc_pc),
+            M::MVAC1 => self.finish(Self::mvac),
+            M::MOVR1 => self.finish(Self::movr),
+            M::OUT1 => self.finish(Self::out),
+            M::ADD1 => self.finish(Self::add),
+            M::SUB1 => self.finish(Self::sub),
+            M::INC1 => self.finish(Self::inc),
+            M::CLAC1 => self.finish(Self::clac),
+            M::AND1 => self.finish(Self::and),
+            M::OR1 => self.finish(Self::or),
+            M::ASHR1 => self.finish(Self::ashr),
+            M::NOT1 => self.finish(Self::not),
+            M::HALT1 => self.finish(Self::halt),
+            M::INT1 => {
+                self.enter_interrupt();
+                M::FETCH1
+            }
+            M::EI1 => self.finish(|e| e.set(R::IE, 1)),
+            M::DI1 => self.finish(|e| e.set(R::IE, 0)),
+            M::RETI1 => self.finish(|e| {
+                e.transfer(R::EPC, R::PC);
+                e.set(R::IE, 1);
+            }),
+            M::EXEC1 => match self.operation() {
+                Some(Operation::Custom(operation)) => {
+                    operation(&mut Context::new(self))?;
+                    self.finish(|_| ())
+                }
+                _ => return Err(self.illegal_opcode()),
+            },
+            M::IMM1 => self.read_operand(true, M::IMM2, M::IMM3)?,
+            M::IMM2 => self.read_operand_high(true, M::IMM3)?,
+            M::IMM3 => match self.operation() {
+                Some(Operation::CustomWithOperand(operation)) => {
+                    let operand = self.operand();
+                    operation(&mut Context::new(self), operand)?;
+                    self.finish(|_| ())
+                }
+                _ => return Err(self.illegal_opcode()),
+            },
+        };
+
+        Ok(next)
+    }
+
+    /// Starts the instruction at PC, checking that it was loaded, with the program or kept from before it.
+    fn begin_instruction(&mut self) -> Result<(), Fault> {
+        let pc = self.registers.get(R::PC);
+        self.instruction_address = pc;
+
+        if !self.memory.loaded(pc) {
+            return Err(Fault::FetchOutOfProgram(pc));
+        }
+        if self.memory.program_range().contains(&pc)
+            && self.layout.get((pc - self.memory.base()) as usize) == Some(&WordKind::Data)
+        {
+            self.guard(self.code_guard, Fault::ExecuteData(pc))?;
+        }
+
+        Ok(())
     }
 
-    fn execute(&mut self, i: Instruction) {
-        use Instruction::*;
-
-        match i {
-            LDAC => self.ldac(),
-            STAC => self.stac(),
-            JMP => self.jmp(),
-            JMPZ => self.jmpz(),
-            INC => self.inc(),
-            MVAC => self.mvac(),
-            MOVR => self.movr(),
-            OUT => self.out(),
-            ADD => self.add(),
-            SUB => self.sub(),
-            ASHR => self.ashr(),
-            NOT => self.not(),
-            OR => self.or(),
-            AND => self.and(),
-            CLAC => self.clac(),
-            HALT => self.halt(),
+    /// Reads the first word of an operand into DR, moving on to 'high' when the address continues in the next word.
+    fn read_operand(
+        &mut self,
+        advance_pc: bool,
+        high: MicroState,
+        done: MicroState,
+    ) -> Result<MicroState, Fault> {
+        self.load_dr()?;
+        if advance_pc {
+            self.inc_pc();
         }
 
+        if self.textbook() {
+            self.inc_ar();
+            Ok(high)
+        } else {
+            Ok(done)
+        }
+    }
+
+    /// Moves the low byte of an operand into TR and reads its high byte into DR.
+    fn read_operand_high(
+        &mut self,
+        advance_pc: bool,
+        done: MicroState,
+    ) -> Result<MicroState, Fault> {
+        self.transfer(R::DR, R::TR);
+        self.load_dr()?;
+        if advance_pc {
+            self.inc_pc();
+        }
+
+        Ok(done)
+    }
+
+    /// Indicates whether an instruction's extension, if it belongs to one, is enabled.
+    fn enabled(&self, i: &Instruction) -> bool {
+        (!i.operation.is_interrupt() || self.interrupt_vector.is_some())
+            && (!i.operation.is_extended_flags() || self.extended_flags)
+    }
+
+    /// Determines the first execute state of an instruction.
+    fn decode(&self, i: Instruction) -> MicroState {
+        let taken = i
+            .operation
+            .condition()
+            .is_some_and(|flag| self.registers.get(flag) == 1);
+
+        i.operation.first_state(taken)
+    }
+
+    /// Faults on an instruction in IR that no longer decodes, as the instruction set was replaced part way through it.
+    fn illegal_opcode(&self) -> Fault {
+        Fault::IllegalOpcode(self.instruction_address, self.registers.get(R::IR))
+    }
+
+    /// The operation of the instruction in IR.
+    fn operation(&self) -> Option<Operation> {
+        self.isa
+            .decode(self.registers.get(R::IR))
+            .map(|instruction| instruction.operation)
+    }
+
+    /// Performs the last transfer of an instruction and returns to fetching.
+    ///
+    /// An interrupt is entered first when one is requested and interrupts are enabled.
+    fn finish(&mut self, transfer: impl FnOnce(&mut Self)) -> MicroState {
+        transfer(self);
         self.update_z();
+
+        if self.interrupt_requested() {
+            M::INT1
+        } else {
+            M::FETCH1
+        }
+    }
+
+    /// Indicates whether an interrupt is to be entered once the current instruction is finished.
+    fn interrupt_requested(&self) -> bool {
+        self.interrupt_vector.is_some()
+            && !self.halted()
+            && self.registers.get(R::IE) == 1
+            && self.memory.interrupt_requested()
     }
 
-    // Fetches the next instruction to be executed.
-    fn fetch(&mut self) -> Instruction {
-        self.registers.transfer(R::PC, R::AR);
-        self.registers.set(R::DR, self.dereference(R::AR));
-        self.inc_pc();
-        self.registers.transfer(R::DR, R::IR);
-        self.registers.transfer(R::PC, R::AR);
-        self.registers.get(R::IR).into()
+    fn enter_interrupt(&mut self) {
+        self.transfer(R::PC, R::EPC);
+        self.set(R::PC, self.interrupt_vector.unwrap_or_default());
+        self.set(R::IE, 0);
     }
 
     fn halt(&mut self) {
-        self.registers.set(R::S, 1);
+        self.set(R::S, 1);
+        self.notify(Notification::Halt);
     }
 
-    // Loads the value of a given address into ACC.
-    fn ldac(&mut self) {
-        self.registers.set(R::DR, self.dereference(R::AR));
-        self.inc_pc();
-        self.registers.transfer(R::DR, R::AR);
-        self.registers.set(R::DR, self.dereference(R::AR));
-        self.registers.transfer(R::DR, R::ACC);
+    // Stores the data register at the address in the address register.
+    fn stac(&mut self) -> Result<(), Fault> {
+        let address = self.registers.get(R::AR);
+        let value = self.registers.get(R::DR);
+        self.drive(ControlSignal::DrBus, value);
+        self.datapath.assert(ControlSignal::BusMem);
+        self.datapath.assert(ControlSignal::Write);
+        self.write(address, value)
     }
 
-    // Stores the current value of ACC at the given address.
-    fn stac(&mut self) {
-        self.registers.set(R::DR, self.dereference(R::AR));
-        self.inc_pc();
-        self.registers.transfer(R::DR, R::AR);
-        self.registers.transfer(R::ACC, R::DR);
+    /// Writes a word to memory or a device, guarding against overwriting executed code.
+    fn write(&mut self, address: u32, value: u32) -> Result<(), Fault> {
+        if self.executed(address) {
+            let fault = Fault::SelfModifyingCode(self.instruction_address, address);
+            self.guard(self.code_guard, fault)?;
+        }
+
         self.memory
-            .set(self.registers.get(R::AR), self.registers.get(R::DR));
-    }
+            .set(address, value)
+            .ok_or(Fault::AddressOutOfRange(self.instruction_address, address))?;
 
-    fn mvac(&mut self) {
-        self.registers.transfer(R::ACC, R::R)
+        self.notify(Notification::MemoryWrite(address, value));
+        Ok(())
     }
 
-    fn movr(&mut self) {
-        self.registers.transfer(R::R, R::ACC)
+    /// Marks a word as executed when it belongs to the instruction under way, as its opcode or an operand.
+    ///
+    /// While the opcode is being read IR still holds the previous instruction, but AR is at the opcode then.
+    fn mark_executed(&mut self, address: u32) {
+        if self.code_guard == Guard::Off {
+            return;
+        }
+
+        let len = match self.isa.decode(self.registers.get(R::IR)) {
+            Some(instruction) if instruction.has_operand() => 1 + self.width.operand_words(),
+            _ => 1,
+        };
+        if address.wrapping_sub(self.instruction_address) < len {
+            self.executed.entry(address).or_insert(self.time_step);
+        }
     }
 
-    // Dereference the address in the address register and give it to the data register then transfer that value to the program counter.
-    fn jmp(&mut self) {
-        self.registers.set(R::DR, self.dereference(R::AR));
-        self.registers.transfer(R::DR, R::PC);
+    /// Indicates whether a word was executed before the current step, see [`Emulator::mark_executed`].
+    fn executed(&self, address: u32) -> bool {
+        self.executed
+            .get(&address)
+            .is_some_and(|&step| step < self.time_step)
     }
 
-    fn jmpz(&mut self) {
-        if self.registers.get(R::Z) == 1 {
-            self.jmp()
-        } else {
-            self.inc_pc()
+    /// Raises or records a fault caught by a guard, depending on how it was configured.
+    fn guard(&mut self, guard: Guard, fault: Fault) -> Result<(), Fault> {
+        match guard {
+            Guard::Off => Ok(()),
+            Guard::Warn => {
+                log::warn!("{fault}");
+                self.warnings.push(Warning {
+                    time_step: self.time_step,
+                    fault,
+                });
+                Ok(())
+            }
+            Guard::Stop => Err(fault),
         }
     }
 
+    fn mvac(&mut self) {
+        self.transfer(R::ACC, R::R)
+    }
+
+    fn movr(&mut self) {
+        self.transfer(R::R, R::ACC)
+    }
+
     fn out(&mut self) {
-        self.registers.transfer(R::ACC, R::OUTR);
+        self.transfer(R::ACC, R::OUTR);
+        let value = self.registers.get(R::OUTR);
+        self.output.push(Output {
+            time_step: self.time_step(),
+            value,
+        });
+        self.notify(Notification::Output(value));
     }
 
     fn sub(&mut self) {
-        self.registers.set(
-            R::ACC,
-            self.registers
-                .get(R::ACC)
-                .wrapping_sub(self.registers.get(R::R)),
-        )
+        let (acc, r) = (self.registers.get(R::ACC), self.registers.get(R::R));
+        self.alu(ControlSignal::AluSub, acc.wrapping_sub(r));
+
+        let result = self.registers.get(R::ACC);
+        let overflow = (acc ^ r) & (acc ^ result) & self.width.sign_bit() != 0;
+        self.update_flags(acc < r, overflow);
     }
 
     fn add(&mut self) {
-        self.registers.set(
-            R::ACC,
-            self.registers
-                .get(R::ACC)
-                .wrapping_add(self.registers.get(R::R)),
-        )
+        let (acc, r) = (self.registers.get(R::ACC), self.registers.get(R::R));
+        self.alu(ControlSignal::AluAdd, acc.wrapping_add(r));
+
+        let result = self.registers.get(R::ACC);
+        let carry = acc as u64 + r as u64 > self.width.data_mask() as u64;
+        let overflow = (acc ^ result) & (r ^ result) & self.width.sign_bit() != 0;
+        self.update_flags(carry, overflow);
     }
 
     fn inc(&mut self) {
-        self.registers
-            .set(R::ACC, self.registers.get(R::ACC).wrapping_add(1))
+        let acc = self.registers.get(R::ACC);
+        self.alu(ControlSignal::AluInc, acc.wrapping_add(1));
+
+        let result = self.registers.get(R::ACC);
+        self.update_flags(result == 0, result == self.width.sign_bit());
     }
 
     fn clac(&mut self) {
-        self.registers.set(R::ACC, 0)
+        self.alu(ControlSignal::AluClear, 0)
     }
 
     fn and(&mut self) {
-        self.registers.set(
-            R::ACC,
+        self.alu(
+            ControlSignal::AluAnd,
             self.registers.get(R::ACC) & self.registers.get(R::R),
         )
     }
 
     fn or(&mut self) {
-        self.registers.set(
-            R::ACC,
+        self.alu(
+            ControlSignal::AluOr,
             self.registers.get(R::ACC) | self.registers.get(R::R),
         )
     }
 
     fn ashr(&mut self) {
-        self.registers.set(R::ACC, self.registers.get(R::ACC) >> 1)
+        let acc = self.registers.get(R::ACC);
+        self.alu(ControlSignal::AluShiftRight, acc >> 1);
+        self.update_flags(acc & 1 == 1, false);
     }
 
     fn not(&mut self) {
-        self.registers.set(R::ACC, !self.registers.get(R::ACC))
+        self.alu(ControlSignal::AluNot, !self.registers.get(R::ACC))
     }
 
     fn inc_pc(&mut self) {
+        self.datapath.assert(ControlSignal::PcInc);
         self.registers
             .set(R::PC, self.registers.get(R::PC).wrapping_add(1))
     }
 
+    fn inc_ar(&mut self) {
+        self.datapath.assert(ControlSignal::ArInc);
+        self.registers
+            .set(R::AR, self.registers.get(R::AR).wrapping_add(1))
+    }
+
+    /// Loads a register, asserting its load signal.
+    fn set(&mut self, reg: Register, val: u32) {
+        self.datapath.assert(ControlSignal::load(reg));
+        self.registers.set(reg, val);
+    }
+
+    /// Copies one register into another, over the bus unless they are wired directly. ACC loads through the ALU.
+    fn transfer(&mut self, src: Register, dest: Register) {
+        let value = self.registers.get(src);
+        let direct = matches!((src, dest), (R::DR, R::IR) | (R::DR, R::TR));
+        match ControlSignal::bus(src) {
+            Some(signal) if !direct => self.drive(signal, value),
+            _ => (),
+        }
+
+        if dest == R::ACC {
+            self.alu(ControlSignal::AluPass, value);
+        } else {
+            self.set(dest, value);
+        }
+    }
+
+    /// Moves the operand address into a register, DR and TR driving the bus together when operands take two words.
+    fn operand_to(&mut self, reg: Register) {
+        if self.textbook() {
+            self.datapath.assert(ControlSignal::TrBus);
+        }
+        self.drive(ControlSignal::DrBus, self.operand());
+        self.set(reg, self.operand());
+    }
+
+    /// Asserts the signal driving the bus with a value.
+    fn drive(&mut self, signal: ControlSignal, value: u32) {
+        self.datapath.assert(signal);
+        self.datapath.bus = Some(value);
+    }
+
+    /// Loads the result of an ALU function into ACC, R driving the bus for those that take it.
+    fn alu(&mut self, function: ControlSignal, result: u32) {
+        use ControlSignal::*;
+
+        if matches!(function, AluAdd | AluSub | AluAnd | AluOr) {
+            self.drive(RBus, self.registers.get(R::R));
+        }
+        self.datapath.assert(function);
+        self.set(R::ACC, result);
+        self.datapath.alu = Some(self.registers.get(R::ACC));
+    }
+
+    fn textbook(&self) -> bool {
+        self.width == WordWidth::Textbook
+    }
+
+    /// The operand address held in DR, with TR holding its low byte when operands take two words.
+    fn operand(&self) -> u32 {
+        if self.textbook() {
+            self.registers.get(R::DR) << 8 | self.registers.get(R::TR)
+        } else {
+            self.registers.get(R::DR)
+        }
+    }
+
     /// Dereferences the current address stored in the given register and retrieves the contents of said address from memory.
-    fn dereference(&self, r: Register) -> u32 {
-        self.memory.get(self.registers.get(r))
+    fn dereference(&mut self, r: Register) -> Result<u32, Fault> {
+        self.read(self.registers.get(r))
+    }
+
+    /// Reads a word from memory or a device, guarding against reading words never written.
+    fn read(&mut self, address: u32) -> Result<u32, Fault> {
+        let value = self
+            .memory
+            .read(address)
+            .ok_or(Fault::AddressOutOfRange(self.instruction_address, address))?;
+        if !self.memory.initialized(address) {
+            let fault = Fault::UninitializedRead(self.instruction_address, address);
+            self.guard(self.uninitialized_guard, fault)?;
+        }
+
+        self.notify(Notification::MemoryRead(address, value));
+        Ok(value)
+    }
+
+    /// Tells every observer about each notification, in order.
+    fn send(&mut self, notifications: &[Notification]) {
+        for (_, observer) in &mut self.observers {
+            for notification in notifications {
+                notification.send(observer.as_mut());
+            }
+        }
+    }
+
+    /// Holds a notification for observers until the current micro-operation completes.
+    fn notify(&mut self, notification: Notification) {
+        if !self.observers.is_empty() {
+            self.notifications.push(notification);
+        }
+    }
+
+    // Reads the word at the address in the address register into the data register.
+    fn load_dr(&mut self) -> Result<(), Fault> {
+        let value = self.dereference(R::AR)?;
+        self.mark_executed(self.registers.get(R::AR));
+        self.datapath.assert(ControlSignal::Read);
+        self.drive(ControlSignal::MemBus, value);
+        self.set(R::DR, value);
+        Ok(())
+    }
+
+    /// Sets C and V as given and N from the sign bit of ACC, when the extended flags are enabled.
+    fn update_flags(&mut self, carry: bool, overflow: bool) {
+        if !self.extended_flags {
+            return;
+        }
+
+        let negative = self.registers.get(R::ACC) & self.width.sign_bit() != 0;
+        self.set(R::C, carry as u32);
+        self.set(R::V, overflow as u32);
+        self.set(R::N, negative as u32);
     }
 
     fn update_z(&mut self) -> bool {
         let z = self.registers.get(R::ACC) == 0;
-        self.registers.set(R::Z, z as u32);
+        self.set(R::Z, z as u32);
         z
     }
 }
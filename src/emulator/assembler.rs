@@ -1,4 +1,8 @@
-use super::util::{Error, Instruction};
+use super::{
+    config::WordWidth,
+    isa::Isa,
+    util::{Error, WordKind},
+};
 use std::collections::HashMap;
 
 /// The assembler parses the assembly file and builds up relevant structures for our emulator and debugger.
@@ -6,7 +10,10 @@ use std::collections::HashMap;
 /// The internal line map is used for our bytecode highlighter to match a given line number to a range of instructions.
 /// Additionally, the assembler will look ahead for other errors to report for the editor to display.
 pub struct Assembler {
+    pub origin: u32,
     pub instructions: Vec<u32>,
+    /// Whether each word of the instructions is code or data.
+    pub word_kinds: Vec<WordKind>,
     pub line_map: HashMap<usize, (usize, usize)>,
     pub symbol_map: HashMap<String, u32>,
     pub symbol_references: HashMap<u32, String>,
@@ -16,7 +23,25 @@ pub struct Assembler {
 impl Assembler {
     /// Parses a given file and produces bytecode for the emulator along with information for the debugger.
     pub fn parse(input: String) -> Assembler {
+        Assembler::parse_at(input, 0)
+    }
+
+    /// Parses a given file with its symbols relocated to be loaded at the 'origin' address.
+    pub fn parse_at(input: String, origin: u32) -> Assembler {
+        Assembler::parse_for(input, origin, WordWidth::default())
+    }
+
+    /// Parses a given file to be loaded at the 'origin' address of a machine with the given word width.
+    ///
+    /// Operands are laid out over as many words as an address takes and initializers must fit in a single word.
+    pub fn parse_for(input: String, origin: u32, width: WordWidth) -> Assembler {
+        Assembler::parse_with(input, origin, width, &Isa::rsc())
+    }
+
+    /// Parses a given file written for the given instruction set, see [`Assembler::parse_for`].
+    pub fn parse_with(input: String, origin: u32, width: WordWidth, isa: &Isa) -> Assembler {
         let mut instructions = Vec::new();
+        let mut word_kinds = Vec::new();
         let mut line_map = HashMap::new();
         let mut symbol_map = HashMap::new();
         let mut to_replace = HashMap::new();
@@ -40,11 +65,12 @@ impl Assembler {
                 }
 
                 // If the first word is an instruction, parse the operand if needed.
-                if let Ok(instruction) = TryInto::<Instruction>::try_into(word) {
+                if let Some(instruction) = isa.lookup(word) {
                     let current_idx = instructions.len();
                     line_map.insert(ln, (current_idx, current_idx));
 
-                    instructions.push(instruction as u32);
+                    instructions.push(instruction.opcode);
+                    word_kinds.push(WordKind::Code);
 
                     // If it requires an operand, ensure one exists, add it to the map.
                     if instruction.has_operand() {
@@ -55,14 +81,15 @@ impl Assembler {
 
                         // Extend the bytecode highlight for the operand.
                         line_map.entry(ln).and_modify(|(_, end)| {
-                            *end += 1;
+                            *end += width.operand_words() as usize;
                         });
 
                         // Add the current position in the bytecode to a map with the variable name.
                         to_replace.insert(instructions.len() as u32, (ln, operand.to_owned()));
 
-                        // Insert a placeholder that is to be replaced.
-                        instructions.push(0);
+                        // Insert placeholders that are to be replaced.
+                        instructions.resize(instructions.len() + width.operand_words() as usize, 0);
+                        word_kinds.resize(instructions.len(), WordKind::Code);
                     }
                 } else {
                     // If not an instruction and it doesn't end with a ":", its an unknown keyword.
@@ -81,7 +108,7 @@ impl Assembler {
                     match words.next() {
                         // No operand, its a label.
                         Some(";") | None => {
-                            symbol_map.insert(name.to_owned(), instructions.len() as u32);
+                            symbol_map.insert(name.to_owned(), origin + instructions.len() as u32);
                         }
                         // It has a operand, it is a variable declaration.
                         Some(value) => {
@@ -90,11 +117,17 @@ impl Assembler {
                                 continue;
                             };
 
+                            if value & !width.data_mask() != 0 {
+                                errors.push(Error::InitializerOutOfRange(name.to_owned(), ln));
+                                continue;
+                            }
+
                             let current_idx = instructions.len();
                             line_map.insert(ln, (current_idx, current_idx));
 
-                            symbol_map.insert(name.to_owned(), current_idx as u32);
+                            symbol_map.insert(name.to_owned(), origin + current_idx as u32);
                             instructions.push(value);
+                            word_kinds.push(WordKind::Data);
                         }
                     }
                 }
@@ -108,8 +141,15 @@ impl Assembler {
                 // Identify if the variable name exists in our symbol map, error if not.
                 match symbol_map.get(&var_name) {
                     Some(symbol) => {
-                        instructions[idx as usize] = *symbol;
-                        Some((idx, var_name))
+                        // Addresses are laid out low byte first when they span multiple words.
+                        let idx = idx as usize;
+                        if width == WordWidth::Textbook {
+                            instructions[idx] = symbol & 0xFF;
+                            instructions[idx + 1] = symbol >> 8 & 0xFF;
+                        } else {
+                            instructions[idx] = *symbol;
+                        }
+                        Some((idx as u32, var_name))
                     }
                     None => {
                         errors.push(Error::UndefinedVariable(var_name.to_string(), ln));
@@ -119,10 +159,12 @@ impl Assembler {
             })
             .collect();
 
-        let errors = (!errors.is_empty()).then(|| errors);
+        let errors = (!errors.is_empty()).then_some(errors);
 
         Assembler {
+            origin,
             instructions,
+            word_kinds,
             line_map,
             symbol_map,
             symbol_references,
@@ -0,0 +1,209 @@
+use super::{
+    assembler::Assembler,
+    memory::DEFAULT_MEMORY_SIZE,
+    util::{Register, REGISTER_COUNT},
+};
+use serde::{Deserialize, Serialize};
+
+/// The widths of the data and address paths of the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WordWidth {
+    /// 8-bit data registers and memory words with a 16-bit AR and PC, as the textbook RSC.
+    ///
+    /// Operands take two memory words, the low byte of the address followed by the high byte.
+    Textbook,
+    /// Every register and memory word is 32 bits wide and operands take a single memory word.
+    #[default]
+    Wide,
+}
+
+impl WordWidth {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Textbook => "8/16-bit",
+            Self::Wide => "32-bit",
+        }
+    }
+
+    /// The mask applied to data registers and memory words.
+    pub fn data_mask(self) -> u32 {
+        match self {
+            Self::Textbook => 0xFF,
+            Self::Wide => u32::MAX,
+        }
+    }
+
+    /// The mask applied to the address registers.
+    pub fn address_mask(self) -> u32 {
+        match self {
+            Self::Textbook => 0xFFFF,
+            Self::Wide => u32::MAX,
+        }
+    }
+
+    /// Truncates a value to the width of the given register.
+    pub fn mask(self, reg: Register, val: u32) -> u32 {
+        match reg {
+            Register::S | Register::Z | Register::IE | Register::C | Register::V | Register::N => {
+                val & 1
+            }
+            Register::AR | Register::PC | Register::EPC => val & self.address_mask(),
+            _ => val & self.data_mask(),
+        }
+    }
+
+    /// The sign bit of data registers and memory words, when they are treated as signed.
+    pub fn sign_bit(self) -> u32 {
+        self.data_mask() & !(self.data_mask() >> 1)
+    }
+
+    /// The number of memory words an operand address occupies.
+    pub fn operand_words(self) -> u32 {
+        match self {
+            Self::Textbook => 2,
+            Self::Wide => 1,
+        }
+    }
+}
+
+/// How much of the timeline is kept for stepping backward.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct History {
+    /// Whether steps are recorded. Running without recording is faster, but only the instruction under way can be
+    /// stepped back.
+    pub record: bool,
+    /// The least number of steps kept, older steps are discarded a checkpoint at a time. Everything is kept when unset.
+    pub limit: Option<usize>,
+    /// The number of steps between full copies of the machine state.
+    ///
+    /// Every checkpoint copies the whole address space, 256KiB for the default 64K words, so a history of 'limit'
+    /// steps holds about 'limit / checkpoint_interval' of them. Shorter intervals make seeking faster at that cost.
+    pub checkpoint_interval: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            record: true,
+            limit: Some(100_000),
+            checkpoint_interval: 10_000,
+        }
+    }
+}
+
+/// What happens when a check on the running program finds a likely bug.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Guard {
+    /// It is allowed, as on the real machine.
+    #[default]
+    Off,
+    /// It is allowed but recorded as a warning.
+    Warn,
+    /// It raises a fault.
+    Stop,
+}
+
+/// The state registers power on and reset to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reset {
+    /// Initial register values, indexed by [`Register`]. PC starts at the base of the program when unset, Z starts
+    /// set to match ACC, and every other register starts at zero.
+    pub registers: [Option<u32>; REGISTER_COUNT],
+    /// Seeds random values for the registers left unset, to catch programs which rely on them starting at zero.
+    /// PC, S and IE are never randomized, and Z still matches ACC.
+    pub randomize: Option<u64>,
+}
+
+impl Reset {
+    /// Sets the value a register starts at.
+    pub fn with_register(mut self, reg: Register, val: u32) -> Self {
+        self.registers[reg as usize] = Some(val);
+        self
+    }
+
+    /// Begins execution at the given address.
+    pub fn with_entry(self, address: u32) -> Self {
+        self.with_register(Register::PC, address)
+    }
+
+    /// Begins execution at a label of an assembled program, if it defines it.
+    pub fn with_entry_label(self, assembler: &Assembler, label: &str) -> Option<Self> {
+        assembler
+            .symbol_map
+            .get(label)
+            .map(|&address| self.with_entry(address))
+    }
+
+    /// The value of every register at reset, for a program loaded at 'base'.
+    pub fn values(&self, base: u32) -> [u32; REGISTER_COUNT] {
+        let mut seed = self.randomize;
+        let mut values = [0; REGISTER_COUNT];
+
+        for reg in Register::iter() {
+            values[*reg as usize] = match (reg, self.registers[*reg as usize]) {
+                (_, Some(val)) => val,
+                (Register::PC, None) => base,
+                (Register::S | Register::IE | Register::Z, None) => 0,
+                (_, None) => seed.as_mut().map_or(0, next_random),
+            };
+        }
+
+        if self.registers[Register::Z as usize].is_none() {
+            values[Register::Z as usize] = (values[Register::ACC as usize] == 0) as u32;
+        }
+        values
+    }
+}
+
+/// Advances a SplitMix64 generator, giving the next value.
+fn next_random(state: &mut u64) -> u32 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) as u32
+}
+
+/// The shape of the machine an emulator is created with.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// The address the program is loaded at and where execution begins.
+    pub base: u32,
+    /// The number of words in the address space.
+    pub memory_size: usize,
+    pub width: WordWidth,
+    pub history: History,
+    /// Guards against fetching from data and storing over executed instructions. Only words classified as data
+    /// through [`Emulator::classify`](super::Emulator::classify) are guarded against being executed.
+    pub code_guard: Guard,
+    /// Guards against reading words outside of the program that have never been written.
+    pub uninitialized_guard: Guard,
+    /// The address interrupts jump to. The interrupt extension, its registers and instructions, is only enabled
+    /// when this is set.
+    pub interrupt_vector: Option<u32>,
+    /// Enables the extended flags, C, V and N set by ADD, SUB, INC and ASHR along with the jumps on them.
+    pub extended_flags: bool,
+    /// Drives the emulator with a microprogrammed control unit running the textbook RSC's microcode, rather than
+    /// the hardwired one. Other microcode can be loaded through
+    /// [`Emulator::set_microcode`](super::Emulator::set_microcode).
+    pub microprogrammed: bool,
+    /// The state registers power on and reset to.
+    pub reset: Reset,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            base: 0,
+            memory_size: DEFAULT_MEMORY_SIZE,
+            width: WordWidth::default(),
+            history: History::default(),
+            code_guard: Guard::default(),
+            uninitialized_guard: Guard::default(),
+            interrupt_vector: None,
+            extended_flags: false,
+            microprogrammed: false,
+            reset: Reset::default(),
+        }
+    }
+}
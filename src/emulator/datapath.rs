@@ -0,0 +1,201 @@
+use super::util::Register;
+use serde::{Deserialize, Serialize};
+
+/// The control signals of the RSC datapath, named as in the textbook.
+///
+/// Registers load from the internal bus, except IR and TR which are wired directly to DR, and PC which is wired
+/// directly to EPC for returning from interrupts. Memory is always addressed by AR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlSignal {
+    ArLoad,
+    ArInc,
+    PcLoad,
+    PcInc,
+    DrLoad,
+    TrLoad,
+    IrLoad,
+    RLoad,
+    AcLoad,
+    ZLoad,
+    OutrLoad,
+    /// Sets S, halting the machine.
+    SLoad,
+    /// Part of the interrupt extension.
+    IeLoad,
+    /// Part of the interrupt extension.
+    EpcLoad,
+    /// Part of the extended flags.
+    CLoad,
+    /// Part of the extended flags.
+    VLoad,
+    /// Part of the extended flags.
+    NLoad,
+    Read,
+    Write,
+    /// Memory drives the bus.
+    MemBus,
+    /// The bus drives memory.
+    BusMem,
+    PcBus,
+    DrBus,
+    TrBus,
+    RBus,
+    AcBus,
+    /// The ALU passes the bus through to ACC.
+    AluPass,
+    AluAdd,
+    AluSub,
+    AluInc,
+    AluClear,
+    AluAnd,
+    AluOr,
+    AluShiftRight,
+    AluNot,
+}
+
+impl ControlSignal {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ArLoad => "ARLOAD",
+            Self::ArInc => "ARINC",
+            Self::PcLoad => "PCLOAD",
+            Self::PcInc => "PCINC",
+            Self::DrLoad => "DRLOAD",
+            Self::TrLoad => "TRLOAD",
+            Self::IrLoad => "IRLOAD",
+            Self::RLoad => "RLOAD",
+            Self::AcLoad => "ACLOAD",
+            Self::ZLoad => "ZLOAD",
+            Self::OutrLoad => "OUTRLOAD",
+            Self::SLoad => "SLOAD",
+            Self::IeLoad => "IELOAD",
+            Self::EpcLoad => "EPCLOAD",
+            Self::CLoad => "CLOAD",
+            Self::VLoad => "VLOAD",
+            Self::NLoad => "NLOAD",
+            Self::Read => "READ",
+            Self::Write => "WRITE",
+            Self::MemBus => "MEMBUS",
+            Self::BusMem => "BUSMEM",
+            Self::PcBus => "PCBUS",
+            Self::DrBus => "DRBUS",
+            Self::TrBus => "TRBUS",
+            Self::RBus => "RBUS",
+            Self::AcBus => "ACBUS",
+            Self::AluPass => "ALUPASS",
+            Self::AluAdd => "ALUADD",
+            Self::AluSub => "ALUSUB",
+            Self::AluInc => "ALUINC",
+            Self::AluClear => "ALUCLEAR",
+            Self::AluAnd => "ALUAND",
+            Self::AluOr => "ALUOR",
+            Self::AluShiftRight => "ALUSHR",
+            Self::AluNot => "ALUNOT",
+        }
+    }
+
+    pub fn iter() -> std::slice::Iter<'static, ControlSignal> {
+        [
+            Self::ArLoad,
+            Self::ArInc,
+            Self::PcLoad,
+            Self::PcInc,
+            Self::DrLoad,
+            Self::TrLoad,
+            Self::IrLoad,
+            Self::RLoad,
+            Self::AcLoad,
+            Self::ZLoad,
+            Self::OutrLoad,
+            Self::SLoad,
+            Self::IeLoad,
+            Self::EpcLoad,
+            Self::CLoad,
+            Self::VLoad,
+            Self::NLoad,
+            Self::Read,
+            Self::Write,
+            Self::MemBus,
+            Self::BusMem,
+            Self::PcBus,
+            Self::DrBus,
+            Self::TrBus,
+            Self::RBus,
+            Self::AcBus,
+            Self::AluPass,
+            Self::AluAdd,
+            Self::AluSub,
+            Self::AluInc,
+            Self::AluClear,
+            Self::AluAnd,
+            Self::AluOr,
+            Self::AluShiftRight,
+            Self::AluNot,
+        ]
+        .iter()
+    }
+
+    /// The signal loading a register.
+    pub fn load(reg: Register) -> Self {
+        match reg {
+            Register::S => Self::SLoad,
+            Register::Z => Self::ZLoad,
+            Register::IR => Self::IrLoad,
+            Register::AR => Self::ArLoad,
+            Register::DR => Self::DrLoad,
+            Register::TR => Self::TrLoad,
+            Register::PC => Self::PcLoad,
+            Register::OUTR => Self::OutrLoad,
+            Register::ACC => Self::AcLoad,
+            Register::R => Self::RLoad,
+            Register::IE => Self::IeLoad,
+            Register::EPC => Self::EpcLoad,
+            Register::C => Self::CLoad,
+            Register::V => Self::VLoad,
+            Register::N => Self::NLoad,
+        }
+    }
+
+    /// The signal driving the bus with a register, for those connected to it.
+    pub fn bus(reg: Register) -> Option<Self> {
+        match reg {
+            Register::PC => Some(Self::PcBus),
+            Register::DR => Some(Self::DrBus),
+            Register::TR => Some(Self::TrBus),
+            Register::R => Some(Self::RBus),
+            Register::ACC => Some(Self::AcBus),
+            _ => None,
+        }
+    }
+}
+
+/// What the datapath did during a single step: the control signals asserted and the values on the bus and
+/// out of the ALU.
+///
+/// When a step drives the bus more than once, as custom microcode may, the last value is kept. The register
+/// transfers of custom instructions are not shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Datapath {
+    // One bit per signal, in the order of ControlSignal::iter.
+    signals: u64,
+    pub bus: Option<u32>,
+    pub alu: Option<u32>,
+}
+
+impl Datapath {
+    /// Indicates whether a control signal was asserted.
+    pub fn asserted(&self, signal: ControlSignal) -> bool {
+        self.signals & 1 << signal as u64 != 0
+    }
+
+    /// Every control signal asserted, in the order of [`ControlSignal::iter`].
+    pub fn signals(&self) -> impl Iterator<Item = ControlSignal> + '_ {
+        ControlSignal::iter()
+            .copied()
+            .filter(|signal| self.asserted(*signal))
+    }
+
+    pub(super) fn assert(&mut self, signal: ControlSignal) {
+        self.signals |= 1 << signal as u64;
+    }
+}
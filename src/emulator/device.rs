@@ -0,0 +1,395 @@
+use super::memory::{Change, TimelessEngine};
+
+/// A peripheral mapped into a range of the address space.
+///
+/// Offsets are relative to the address the device is attached at. Devices keep their state in a
+/// [`DeviceState`] so that it steps forward and backward in time alongside memory.
+pub trait Device {
+    fn name(&self) -> &'static str;
+
+    /// The number of words the device occupies in the address space.
+    fn size(&self) -> u32;
+
+    /// Reads a word as the CPU would, which may have side effects such as consuming input.
+    fn read(&mut self, offset: u32) -> u32;
+
+    /// Reads a word without any side effects, used for display.
+    fn peek(&self, offset: u32) -> u32;
+
+    fn write(&mut self, offset: u32, val: u32);
+
+    /// Called once for every micro-operation performed.
+    fn tick(&mut self) {}
+
+    /// Indicates whether the device is raising the interrupt request line.
+    fn interrupt(&self) -> bool {
+        false
+    }
+
+    /// A short summary of the device's contents for display.
+    fn describe(&self) -> String;
+
+    fn state(&self) -> &DeviceState;
+
+    fn state_mut(&mut self) -> &mut DeviceState;
+}
+
+/// Reversible storage for the internal words of a device.
+pub struct DeviceState {
+    words: Vec<u32>,
+    // The words the device powers on with.
+    initial: Vec<u32>,
+    engine: TimelessEngine,
+}
+
+impl DeviceState {
+    pub fn new(words: Vec<u32>) -> Self {
+        DeviceState {
+            initial: words.clone(),
+            words,
+            engine: TimelessEngine::new(),
+        }
+    }
+
+    /// Retrieves the given word, words past the end read as zero.
+    pub fn get(&self, idx: usize) -> u32 {
+        self.words.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Sets the given word, growing the storage if needed.
+    pub fn set(&mut self, idx: usize, val: u32) {
+        if idx >= self.words.len() {
+            self.words.resize(idx + 1, 0);
+        }
+
+        self.engine.add_change(idx, self.words[idx], val);
+        self.words[idx] = val;
+    }
+
+    /// Every word of the device's state.
+    pub fn words(&self) -> &[u32] {
+        &self.words
+    }
+
+    /// The step in time the device's state belongs to.
+    pub fn time_step(&self) -> usize {
+        self.engine.time_step()
+    }
+
+    /// Indicates whether every word is as it was at the given step.
+    pub fn unchanged_since(&self, time_step: usize) -> bool {
+        self.engine.unchanged_since(time_step)
+    }
+
+    /// Begins the history of the device at the given step in time.
+    pub fn start_at(&mut self, time_step: usize) {
+        self.engine = TimelessEngine::starting_at(time_step);
+    }
+
+    /// Returns to the words the device powered on with, forgetting every step recorded.
+    pub fn reset(&mut self) {
+        self.words = self.initial.clone();
+        self.engine = TimelessEngine::new();
+    }
+
+    /// Returns to the words of another recorded step.
+    pub fn restore(&mut self, time_step: usize, words: Vec<u32>) {
+        self.engine.jump_to(time_step);
+        self.words = words;
+    }
+
+    /// Forgets every step recorded after the current one.
+    pub fn truncate_future(&mut self) {
+        self.engine.truncate_future();
+    }
+
+    /// Discards the history of every step before the given one.
+    pub fn discard_before(&mut self, time_step: usize) {
+        self.engine.discard_before(time_step);
+    }
+
+    pub fn step_forward(&mut self) {
+        self.engine.step_forward()
+    }
+
+    pub fn step_backward(&mut self) -> bool {
+        self.engine.step_backward().is_some_and(|changes| {
+            for Change(idx, previous, _) in changes.iter().rev() {
+                self.words[*idx] = *previous;
+            }
+            true
+        })
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.engine.redo().is_some_and(|changes| {
+            for Change(idx, _, val) in changes {
+                self.words[*idx] = *val;
+            }
+            true
+        })
+    }
+
+    /// Moves through the recorded history to the given step, or as close to it as is held.
+    pub fn seek(&mut self, time_step: usize) {
+        while self.time_step() > time_step && self.step_backward() {}
+        while self.time_step() < time_step && self.redo() {}
+    }
+
+    /// Undoes the changes made in the current, unfinished step.
+    pub fn rollback(&mut self) {
+        for Change(idx, previous, _) in self.engine.rollback().rev() {
+            self.words[idx] = previous;
+        }
+    }
+}
+
+/// A queue of values for the program to read, one per read of offset 0.
+///
+/// Offset 1 holds the number of values that have yet to be read. Reading an empty queue gives 0. An interrupt is
+/// requested for as long as there are values to be read.
+pub struct InputQueue {
+    // The read position followed by the queued values.
+    state: DeviceState,
+}
+
+impl InputQueue {
+    pub fn new(values: &[u32]) -> Self {
+        let mut words = vec![0];
+        words.extend_from_slice(values);
+
+        InputQueue {
+            state: DeviceState::new(words),
+        }
+    }
+
+    fn remaining(&self) -> u32 {
+        (self.state.words.len() as u32 - 1).saturating_sub(self.state.get(0))
+    }
+}
+
+impl Device for InputQueue {
+    fn name(&self) -> &'static str {
+        "Input"
+    }
+
+    fn size(&self) -> u32 {
+        2
+    }
+
+    fn read(&mut self, offset: u32) -> u32 {
+        let value = self.peek(offset);
+
+        if offset == 0 && self.remaining() > 0 {
+            self.state.set(0, self.state.get(0) + 1);
+        }
+
+        value
+    }
+
+    fn peek(&self, offset: u32) -> u32 {
+        match offset {
+            0 if self.remaining() > 0 => self.state.get(self.state.get(0) as usize + 1),
+            1 => self.remaining(),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, _offset: u32, _val: u32) {}
+
+    fn interrupt(&self) -> bool {
+        self.remaining() > 0
+    }
+
+    fn describe(&self) -> String {
+        format!("{} remaining", self.remaining())
+    }
+
+    fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut DeviceState {
+        &mut self.state
+    }
+}
+
+/// A character terminal, every write to offset 0 prints the low byte as a character.
+pub struct Terminal {
+    // The number of characters printed followed by the characters themselves.
+    state: DeviceState,
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        Terminal {
+            state: DeviceState::new(vec![0]),
+        }
+    }
+
+    /// Everything printed to the terminal so far.
+    pub fn text(&self) -> String {
+        let len = self.state.get(0) as usize;
+        self.state.words[1..=len]
+            .iter()
+            .map(|&c| char::from(c as u8))
+            .collect()
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Terminal::new()
+    }
+}
+
+impl Device for Terminal {
+    fn name(&self) -> &'static str {
+        "Terminal"
+    }
+
+    fn size(&self) -> u32 {
+        1
+    }
+
+    fn read(&mut self, offset: u32) -> u32 {
+        self.peek(offset)
+    }
+
+    fn peek(&self, _offset: u32) -> u32 {
+        0
+    }
+
+    fn write(&mut self, _offset: u32, val: u32) {
+        let len = self.state.get(0) as usize;
+        self.state.set(len + 1, val & 0xFF);
+        self.state.set(0, len as u32 + 1);
+    }
+
+    fn describe(&self) -> String {
+        format!("{:?}", self.text())
+    }
+
+    fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut DeviceState {
+        &mut self.state
+    }
+}
+
+/// A counter which increases on every micro-operation, writing to it sets the count.
+pub struct Timer {
+    state: DeviceState,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            state: DeviceState::new(vec![0]),
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer::new()
+    }
+}
+
+impl Device for Timer {
+    fn name(&self) -> &'static str {
+        "Timer"
+    }
+
+    fn size(&self) -> u32 {
+        1
+    }
+
+    fn read(&mut self, offset: u32) -> u32 {
+        self.peek(offset)
+    }
+
+    fn peek(&self, _offset: u32) -> u32 {
+        self.state.get(0)
+    }
+
+    fn write(&mut self, _offset: u32, val: u32) {
+        self.state.set(0, val);
+    }
+
+    fn tick(&mut self) {
+        self.state.set(0, self.state.get(0).wrapping_add(1));
+    }
+
+    fn describe(&self) -> String {
+        self.state.get(0).to_string()
+    }
+
+    fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut DeviceState {
+        &mut self.state
+    }
+}
+
+/// A bank of LEDs or seven-segment digits, each word holds the segments lit on one of them.
+pub struct LedBank {
+    count: u32,
+    state: DeviceState,
+}
+
+impl LedBank {
+    pub fn new(count: u32) -> Self {
+        LedBank {
+            count,
+            state: DeviceState::new(vec![0; count as usize]),
+        }
+    }
+
+    /// The segments lit on each LED or digit.
+    pub fn segments(&self) -> &[u32] {
+        &self.state.words
+    }
+}
+
+impl Device for LedBank {
+    fn name(&self) -> &'static str {
+        "LEDs"
+    }
+
+    fn size(&self) -> u32 {
+        self.count
+    }
+
+    fn read(&mut self, offset: u32) -> u32 {
+        self.peek(offset)
+    }
+
+    fn peek(&self, offset: u32) -> u32 {
+        self.state.get(offset as usize)
+    }
+
+    fn write(&mut self, offset: u32, val: u32) {
+        self.state.set(offset as usize, val);
+    }
+
+    fn describe(&self) -> String {
+        self.segments()
+            .iter()
+            .map(|segments| format!("{segments:08b}"))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut DeviceState {
+        &mut self.state
+    }
+}
@@ -0,0 +1,226 @@
+use super::{
+    datapath::Datapath,
+    memory::Image,
+    profile::Profile,
+    util::{Register, REGISTER_COUNT},
+    Control, Emulator,
+};
+use serde::{Deserialize, Serialize};
+
+/// A full copy of the machine state at a step in time.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    time_step: usize,
+    registers: [u32; REGISTER_COUNT],
+    memory: Image,
+    control: Control,
+    profile: Profile,
+}
+
+impl Checkpoint {
+    /// The step in time the checkpoint was taken at.
+    pub fn time_step(&self) -> usize {
+        self.time_step
+    }
+}
+
+impl Emulator {
+    /// The oldest step in time that can still be returned to.
+    pub fn first_step(&self) -> usize {
+        self.first_step
+    }
+
+    /// Returns to the oldest step in time that is still held, the steps since can still be redone.
+    pub fn restart(&mut self) {
+        self.seek(self.first_step);
+    }
+
+    /// Moves to any recorded step in time, in either direction.
+    ///
+    /// The state is restored from the nearest checkpoint at or before the step when that is closer than
+    /// the current step, and the remaining steps are undone or redone. Steps outside of the recorded
+    /// history are clamped to it.
+    pub fn seek(&mut self, time_step: usize) {
+        let time_step = time_step.clamp(self.first_step, self.last_step());
+        let distance = self.time_step.abs_diff(time_step);
+
+        let nearest = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.time_step <= time_step);
+        if let Some(checkpoint) = nearest.filter(|c| time_step - c.time_step < distance) {
+            let control = checkpoint.control;
+            self.time_step = checkpoint.time_step;
+            self.registers.restore(self.time_step, checkpoint.registers);
+            self.memory.restore(self.time_step, &checkpoint.memory);
+            self.profile = checkpoint.profile.clone();
+            self.set_control(control);
+        }
+
+        while self.time_step > time_step && self.step_backward() {}
+        while self.time_step < time_step && self.redo() {}
+    }
+
+    /// The address of the instruction the step from 'time_step' to the one after it belonged to.
+    pub fn instruction_address_at(&self, time_step: usize) -> Option<u32> {
+        // FETCH1 sets the instruction address, so it is taken from the control state after the step.
+        if time_step >= self.time_step {
+            return None;
+        }
+
+        let control = self
+            .trace
+            .get(time_step.checked_sub(self.first_step)? + 1)?;
+        Some(control.instruction_address)
+    }
+
+    /// What the datapath did during the step from 'time_step' to the one after it.
+    pub fn datapath_at(&self, time_step: usize) -> Option<Datapath> {
+        if time_step >= self.time_step {
+            return None;
+        }
+
+        let control = self
+            .trace
+            .get(time_step.checked_sub(self.first_step)? + 1)?;
+        Some(control.datapath)
+    }
+
+    /// The word at an address as it was at an earlier step, or the current one.
+    pub fn value_at(&self, address: u32, time_step: usize) -> Option<u32> {
+        let current = self.memory.get(address)?;
+
+        Some(
+            (time_step.max(self.first_step)..self.time_step)
+                .find_map(|step| {
+                    self.memory
+                        .changes(step)
+                        .iter()
+                        .find(|change| change.0 == address as usize)
+                        .map(|change| change.1)
+                })
+                .unwrap_or(current),
+        )
+    }
+
+    /// Checks that the history restored from a snapshot hangs together, naming the first part that doesn't.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let (first_step, last_step) = (self.first_step, self.first_step + self.trace.len());
+        if self.trace.is_empty() || !(first_step..last_step).contains(&self.time_step) {
+            return Err("trace");
+        }
+        let last_step = last_step - 1;
+
+        if self.memory.base() as usize + self.program.len() > self.memory.size()
+            || self.memory.program_range().len() != self.program.len()
+        {
+            return Err("program");
+        }
+        if !self
+            .registers
+            .holds(first_step, self.time_step, last_step, self.width)
+        {
+            return Err("registers");
+        }
+        if !self
+            .memory
+            .holds(first_step, self.time_step, last_step, self.width)
+        {
+            return Err("memory");
+        }
+        if !self.checkpoints.iter().all(|checkpoint| {
+            (first_step..=last_step).contains(&checkpoint.time_step)
+                && Register::iter().all(|reg| {
+                    let val = checkpoint.registers[*reg as usize];
+                    self.width.mask(*reg, val) == val
+                })
+                && self.memory.fits(&checkpoint.memory)
+        }) {
+            return Err("checkpoints");
+        }
+        Ok(())
+    }
+
+    /// Indicates whether steps are being recorded so that they can be stepped back through.
+    pub fn recording(&self) -> bool {
+        self.history.record
+    }
+
+    /// Turns recording of the steps that follow on or off.
+    ///
+    /// Turning it off forgets every step held other than the current one, turning it back on begins a new history
+    /// at the current step. While it is off only the steps of the instruction under way are held, so that a faulting
+    /// [`Emulator::cycle`] can still undo all of it.
+    pub fn set_recording(&mut self, recording: bool) {
+        if !recording {
+            self.truncate_future();
+            self.discard_before(self.time_step);
+            self.checkpoints.clear();
+        }
+
+        self.history.record = recording;
+
+        if recording {
+            self.checkpoint();
+        }
+    }
+
+    /// Takes a checkpoint when one is due and discards history beyond the limit, called after every step.
+    pub(super) fn record_history(&mut self) {
+        let time_step = self.time_step();
+
+        if time_step.is_multiple_of(self.history.checkpoint_interval.max(1)) {
+            self.checkpoint();
+        }
+
+        let Some(limit) = self.history.limit else {
+            return;
+        };
+
+        // Keep at least 'limit' steps, discarding up to the newest checkpoint that allows it.
+        let Some(oldest) = self
+            .checkpoints
+            .iter()
+            .rev()
+            .map(|checkpoint| checkpoint.time_step)
+            .find(|&step| step + limit <= time_step)
+        else {
+            return;
+        };
+
+        if oldest > self.first_step {
+            self.discard_before(oldest);
+        }
+    }
+
+    /// Discards every step before the given one, which becomes the oldest step that can be returned to.
+    pub(super) fn discard_before(&mut self, time_step: usize) {
+        self.registers.discard_before(time_step);
+        self.memory.discard_before(time_step);
+        self.trace.drain(..time_step - self.first_step);
+        self.checkpoints
+            .retain(|checkpoint| checkpoint.time_step >= time_step);
+        self.first_step = time_step;
+    }
+
+    /// Records a full copy of the current machine state.
+    pub(super) fn checkpoint(&mut self) {
+        let time_step = self.time_step();
+        while self
+            .checkpoints
+            .back()
+            .is_some_and(|checkpoint| checkpoint.time_step >= time_step)
+        {
+            self.checkpoints.pop_back();
+        }
+
+        self.checkpoints.push_back(Checkpoint {
+            time_step,
+            registers: self.registers.values(),
+            memory: self.memory.image(),
+            control: self.control(),
+            profile: self.profile.clone(),
+        });
+    }
+}
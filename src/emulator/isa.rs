@@ -0,0 +1,295 @@
+use super::{
+    config::WordWidth,
+    util::{Fault, MicroState, Register, WordKind},
+    Emulator,
+};
+
+/// What an instruction does once it has been fetched.
+///
+/// Built-in operations are performed with the micro-operations of the textbook RSC. Custom operations make their own
+/// register transfers and memory accesses in a single micro-operation, after reading an operand if they take one.
+#[derive(Debug, Clone, Copy)]
+pub enum Operation {
+    Load,
+    Store,
+    Jump,
+    JumpIfZero,
+    /// Moves ACC into R.
+    MoveToR,
+    /// Moves R into ACC.
+    MoveFromR,
+    Out,
+    Sub,
+    Add,
+    Inc,
+    Clear,
+    And,
+    Or,
+    ShiftRight,
+    Not,
+    Halt,
+    /// Part of the interrupt extension.
+    EnableInterrupts,
+    /// Part of the interrupt extension.
+    DisableInterrupts,
+    /// Part of the interrupt extension.
+    ReturnFromInterrupt,
+    /// Part of the extended flags.
+    JumpIfCarry,
+    /// Part of the extended flags.
+    JumpIfOverflow,
+    /// Part of the extended flags.
+    JumpIfNegative,
+    Custom(fn(&mut Context) -> Result<(), Fault>),
+    /// Given the operand that follows the opcode.
+    CustomWithOperand(fn(&mut Context, u32) -> Result<(), Fault>),
+}
+
+/// The machine as a custom operation sees it.
+///
+/// Registers set and words read or written through it are recorded, guarded and observed as they are for built-in
+/// operations, and a fault undoes all of them.
+pub struct Context<'a> {
+    emulator: &'a mut Emulator,
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn new(emulator: &'a mut Emulator) -> Self {
+        Context { emulator }
+    }
+
+    pub fn get(&self, reg: Register) -> u32 {
+        self.emulator.registers.get(reg)
+    }
+
+    pub fn set(&mut self, reg: Register, val: u32) {
+        self.emulator.set(reg, val);
+    }
+
+    /// Reads the word at an address as the CPU would.
+    pub fn read(&mut self, address: u32) -> Result<u32, Fault> {
+        self.emulator.read(address)
+    }
+
+    /// Writes a word to an address as the CPU would.
+    pub fn write(&mut self, address: u32, val: u32) -> Result<(), Fault> {
+        self.emulator.write(address, val)
+    }
+}
+
+impl Operation {
+    pub fn has_operand(&self) -> bool {
+        matches!(
+            self,
+            Self::Load | Self::Store | Self::Jump | Self::CustomWithOperand(_)
+        ) || self.condition().is_some()
+    }
+
+    /// The flag a conditional jump is taken on.
+    pub fn condition(&self) -> Option<Register> {
+        match self {
+            Self::JumpIfZero => Some(Register::Z),
+            Self::JumpIfCarry => Some(Register::C),
+            Self::JumpIfOverflow => Some(Register::V),
+            Self::JumpIfNegative => Some(Register::N),
+            _ => None,
+        }
+    }
+
+    /// The first execute state of the operation, 'taken' is only considered for conditional jumps.
+    pub fn first_state(&self, taken: bool) -> MicroState {
+        use MicroState as M;
+
+        match self {
+            Self::Load => M::LDAC1,
+            Self::Store => M::STAC1,
+            Self::Jump => M::JMP1,
+            Self::JumpIfZero | Self::JumpIfCarry | Self::JumpIfOverflow | Self::JumpIfNegative
+                if taken =>
+            {
+                M::JMPZY1
+            }
+            Self::JumpIfZero | Self::JumpIfCarry | Self::JumpIfOverflow | Self::JumpIfNegative => {
+                M::JMPZN1
+            }
+            Self::Inc => M::INC1,
+            Self::MoveToR => M::MVAC1,
+            Self::MoveFromR => M::MOVR1,
+            Self::Out => M::OUT1,
+            Self::Add => M::ADD1,
+            Self::Sub => M::SUB1,
+            Self::ShiftRight => M::ASHR1,
+            Self::Not => M::NOT1,
+            Self::Or => M::OR1,
+            Self::And => M::AND1,
+            Self::Clear => M::CLAC1,
+            Self::Halt => M::HALT1,
+            Self::EnableInterrupts => M::EI1,
+            Self::DisableInterrupts => M::DI1,
+            Self::ReturnFromInterrupt => M::RETI1,
+            Self::Custom(_) => M::EXEC1,
+            Self::CustomWithOperand(_) => M::IMM1,
+        }
+    }
+
+    /// Indicates whether the operation belongs to the interrupt extension.
+    pub fn is_interrupt(&self) -> bool {
+        matches!(
+            self,
+            Self::EnableInterrupts | Self::DisableInterrupts | Self::ReturnFromInterrupt
+        )
+    }
+
+    /// Indicates whether the operation belongs to the extended flags.
+    pub fn is_extended_flags(&self) -> bool {
+        matches!(
+            self,
+            Self::JumpIfCarry | Self::JumpIfOverflow | Self::JumpIfNegative
+        )
+    }
+}
+
+/// A single instruction of an instruction set.
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction {
+    pub mnemonic: &'static str,
+    pub opcode: u32,
+    pub operation: Operation,
+}
+
+impl Instruction {
+    pub const fn new(mnemonic: &'static str, opcode: u32, operation: Operation) -> Self {
+        Instruction {
+            mnemonic,
+            opcode,
+            operation,
+        }
+    }
+
+    pub fn has_operand(&self) -> bool {
+        self.operation.has_operand()
+    }
+}
+
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Self) -> bool {
+        self.mnemonic == other.mnemonic && self.opcode == other.opcode
+    }
+}
+
+impl Eq for Instruction {}
+
+/// The instructions the assembler, emulator and interface understand, looked up by mnemonic or opcode.
+#[derive(Debug, Clone)]
+pub struct Isa {
+    instructions: Vec<Instruction>,
+}
+
+impl Isa {
+    /// The sixteen instructions of the textbook RSC, along with those of the interrupt extension and extended flags.
+    pub fn rsc() -> Self {
+        use Operation::*;
+
+        Isa {
+            instructions: vec![
+                Instruction::new("HALT", 0, Halt),
+                Instruction::new("LDAC", 1, Load),
+                Instruction::new("STAC", 2, Store),
+                Instruction::new("MVAC", 3, MoveToR),
+                Instruction::new("MOVR", 4, MoveFromR),
+                Instruction::new("JMP", 5, Jump),
+                Instruction::new("JMPZ", 6, JumpIfZero),
+                Instruction::new("OUT", 7, Out),
+                Instruction::new("SUB", 8, Sub),
+                Instruction::new("ADD", 9, Add),
+                Instruction::new("INC", 10, Inc),
+                Instruction::new("CLAC", 11, Clear),
+                Instruction::new("AND", 12, And),
+                Instruction::new("OR", 13, Or),
+                Instruction::new("ASHR", 14, ShiftRight),
+                Instruction::new("NOT", 15, Not),
+                Instruction::new("EI", 16, EnableInterrupts),
+                Instruction::new("DI", 17, DisableInterrupts),
+                Instruction::new("RETI", 18, ReturnFromInterrupt),
+                Instruction::new("JMPC", 19, JumpIfCarry),
+                Instruction::new("JMPV", 20, JumpIfOverflow),
+                Instruction::new("JMPN", 21, JumpIfNegative),
+            ],
+        }
+    }
+
+    /// Adds an instruction, replacing any with the same mnemonic or opcode.
+    pub fn with(mut self, instruction: Instruction) -> Self {
+        self.instructions
+            .retain(|i| i.mnemonic != instruction.mnemonic && i.opcode != instruction.opcode);
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Removes the instruction with the given mnemonic.
+    pub fn without(mut self, mnemonic: &str) -> Self {
+        self.instructions.retain(|i| i.mnemonic != mnemonic);
+        self
+    }
+
+    /// The instruction an opcode decodes to.
+    pub fn decode(&self, opcode: u32) -> Option<Instruction> {
+        self.instructions
+            .iter()
+            .find(|i| i.opcode == opcode)
+            .copied()
+    }
+
+    /// The instruction with the given mnemonic, as written in source.
+    pub fn lookup(&self, mnemonic: &str) -> Option<Instruction> {
+        self.instructions
+            .iter()
+            .find(|i| i.mnemonic == mnemonic)
+            .copied()
+    }
+
+    /// Every instruction, in the order they were defined.
+    pub fn iter(&self) -> impl Iterator<Item = &Instruction> {
+        self.instructions.iter()
+    }
+
+    /// Disassembles the instruction at the start of 'words', giving its text and the number of words it takes.
+    ///
+    /// Words which are data, or don't decode, are shown as their value.
+    pub fn disassemble(&self, words: &[u32], kind: WordKind, width: WordWidth) -> (String, usize) {
+        let Some(&opcode) = words.first() else {
+            return (String::new(), 0);
+        };
+
+        match self.decode(opcode).filter(|_| kind == WordKind::Code) {
+            Some(instruction) if instruction.has_operand() => {
+                // Addresses are laid out low byte first when they span multiple words.
+                let len = 1 + width.operand_words() as usize;
+                let operand = match width {
+                    WordWidth::Textbook => words[1..len.min(words.len())]
+                        .iter()
+                        .rev()
+                        .fold(0, |operand, word| operand << 8 | word),
+                    WordWidth::Wide => words.get(1).copied().unwrap_or(0),
+                };
+                (format!("{} {operand:#x}", instruction.mnemonic), len)
+            }
+            Some(instruction) => (instruction.mnemonic.to_string(), 1),
+            None => (format!("{opcode:#x}"), 1),
+        }
+    }
+}
+
+impl FromIterator<Instruction> for Isa {
+    fn from_iter<T: IntoIterator<Item = Instruction>>(iter: T) -> Self {
+        Isa {
+            instructions: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Default for Isa {
+    fn default() -> Self {
+        Isa::rsc()
+    }
+}
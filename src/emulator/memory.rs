@@ -1,53 +1,193 @@
-use super::util::Register;
+use super::{
+    config::WordWidth,
+    device::Device,
+    util::{Register, REGISTER_COUNT},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
 
-struct Change(usize, u32);
+/// The number of words in the default RSC address space.
+pub const DEFAULT_MEMORY_SIZE: usize = 1 << 16;
 
+/// A change made to a single slot, its index along with the value before and after.
+#[derive(Serialize, Deserialize)]
+pub struct Change(pub usize, pub u32, pub u32);
+
+/// Records the changes made in every step so that they can be undone and redone.
+#[derive(Serialize, Deserialize)]
 pub struct TimelessEngine {
     time_step: usize,
+    // The step in time the first vector of changes belongs to, earlier changes have been discarded.
+    first_step: usize,
     changes: Vec<Vec<Change>>,
 }
 
+impl Default for TimelessEngine {
+    fn default() -> Self {
+        TimelessEngine::new()
+    }
+}
+
 impl TimelessEngine {
     pub fn new() -> Self {
+        TimelessEngine::starting_at(0)
+    }
+
+    /// Creates an engine whose history begins at the given step in time.
+    pub fn starting_at(time_step: usize) -> Self {
         TimelessEngine {
-            time_step: 0,
+            time_step,
+            first_step: time_step,
             changes: vec![vec![]],
         }
     }
 
+    /// The current step in time.
+    pub fn time_step(&self) -> usize {
+        self.time_step
+    }
+
+    /// The furthest step in time that has been recorded.
+    pub fn last_step(&self) -> usize {
+        self.first_step + self.changes.len() - 1
+    }
+
     /// Increases our current step in time by 1.
     pub fn step_forward(&mut self) {
         self.time_step += 1;
 
-        if self.changes.get(self.time_step).is_none() {
+        if self.changes.get(self.time_step - self.first_step).is_none() {
             self.changes.push(Vec::new())
         }
     }
 
-    /// Steps backward one step in time, only drains the contents of the vector holding the previous step's changes.
-    pub fn step_backward(&mut self) -> Option<std::vec::Drain<'_, Change>> {
-        if self.time_step > 0 {
-            self.time_step -= 1;
+    /// Steps backward one step in time, giving the changes of the step to be undone.
+    ///
+    /// Returns nothing when already at the oldest step still held.
+    pub fn step_backward(&mut self) -> Option<&[Change]> {
+        if self.time_step == self.first_step {
+            return None;
         }
 
-        self.changes.get_mut(self.time_step).map(|v| v.drain(..))
+        self.time_step -= 1;
+        Some(&self.changes[self.time_step - self.first_step])
+    }
+
+    /// Steps forward one recorded step in time, giving the changes of the step to be redone.
+    ///
+    /// Returns nothing when already at the furthest step recorded.
+    pub fn redo(&mut self) -> Option<&[Change]> {
+        if self.time_step == self.last_step() {
+            return None;
+        }
+
+        self.time_step += 1;
+        Some(&self.changes[self.time_step - 1 - self.first_step])
+    }
+
+    /// The changes made in the step from 'time_step' to the one after it, empty when the step isn't held.
+    pub fn changes(&self, time_step: usize) -> &[Change] {
+        time_step
+            .checked_sub(self.first_step)
+            .and_then(|idx| self.changes.get(idx))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Drains the changes made so far in the current, unfinished step.
+    pub fn rollback(&mut self) -> std::vec::Drain<'_, Change> {
+        self.changes[self.time_step - self.first_step].drain(..)
     }
 
     /// Adds a change to the existing vector of changes or creates a new one for the current time step.
-    pub fn add_change(&mut self, idx: usize, value: u32) {
-        self.changes[self.time_step].push(Change(idx, value));
+    pub fn add_change(&mut self, idx: usize, previous: u32, value: u32) {
+        self.changes[self.time_step - self.first_step].push(Change(idx, previous, value));
+    }
+
+    /// Indicates whether every slot holds the same value as it did at the given step, even if it was changed since.
+    ///
+    /// Only the steps still held are considered.
+    pub fn unchanged_since(&self, time_step: usize) -> bool {
+        let mut net: HashMap<usize, (u32, u32)> = HashMap::new();
+        for step in time_step..self.time_step {
+            for Change(idx, previous, value) in self.changes(step) {
+                net.entry(*idx).or_insert((*previous, *value)).1 = *value;
+            }
+        }
+
+        net.values().all(|(before, after)| before == after)
+    }
+
+    /// Forgets every step recorded after the current one, so that a different future can be recorded.
+    pub fn truncate_future(&mut self) {
+        self.changes.truncate(self.time_step - self.first_step + 1);
+        self.changes[self.time_step - self.first_step].clear();
+    }
+
+    /// Discards every change made before the given step, which becomes the oldest step that can be returned to.
+    pub fn discard_before(&mut self, time_step: usize) {
+        let time_step = time_step.clamp(self.first_step, self.time_step);
+        self.changes.drain(..time_step - self.first_step);
+        self.first_step = time_step;
+    }
+
+    /// Indicates whether the engine is at 'time_step', holds every step from 'first_step' to 'last_step' and only
+    /// changes slots below 'slots', between values that 'fits' accepts for the slot.
+    pub fn holds(
+        &self,
+        first_step: usize,
+        time_step: usize,
+        last_step: usize,
+        slots: usize,
+        fits: impl Fn(usize, u32) -> bool,
+    ) -> bool {
+        !self.changes.is_empty()
+            && self.time_step == time_step
+            && self.first_step <= first_step
+            && self.last_step() >= last_step
+            && self
+                .changes
+                .iter()
+                .flatten()
+                .all(|Change(slot, previous, value)| {
+                    *slot < slots && fits(*slot, *previous) && fits(*slot, *value)
+                })
+    }
+
+    /// Moves to another recorded step without applying any changes.
+    ///
+    /// Used when the state at that step is restored from a checkpoint instead.
+    pub fn jump_to(&mut self, time_step: usize) {
+        self.time_step = time_step.clamp(self.first_step, self.last_step());
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Registers {
-    registers: [u32; 9],
+    registers: [u32; REGISTER_COUNT],
+    width: WordWidth,
     engine: TimelessEngine,
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Registers::new()
+    }
+}
+
 impl Registers {
     pub fn new() -> Self {
+        Registers::with_width(WordWidth::default())
+    }
+
+    /// Creates the registers of a machine with the given word width, every write is truncated to fit.
+    pub fn with_width(width: WordWidth) -> Self {
+        let mut registers = [0; REGISTER_COUNT];
+        registers[Register::Z as usize] = 1;
+
         Registers {
-            registers: [0, 1, 0, 0, 0, 0, 0, 0, 0],
+            registers,
+            width,
             engine: TimelessEngine::new(),
         }
     }
@@ -59,11 +199,26 @@ impl Registers {
 
     /// Sets the registers content to the passed value.
     pub fn set(&mut self, reg: Register, val: u32) {
+        let val = self.width.mask(reg, val);
         self.engine
-            .add_change(reg as usize, self.registers[reg as usize]);
+            .add_change(reg as usize, self.registers[reg as usize], val);
         self.registers[reg as usize] = val
     }
 
+    /// Sets the registers content without recording it in the timeline, used for the initial state.
+    pub fn initialize(&mut self, reg: Register, val: u32) {
+        self.registers[reg as usize] = self.width.mask(reg, val)
+    }
+
+    /// Returns to the given values, forgetting every step recorded.
+    pub fn reset(&mut self, values: [u32; REGISTER_COUNT]) {
+        self.engine = TimelessEngine::new();
+
+        for (reg, val) in Register::iter().zip(values) {
+            self.initialize(*reg, val);
+        }
+    }
+
     /// Transfers the source register contents to the destination register.
     pub fn transfer(&mut self, src: Register, dest: Register) {
         self.set(dest, self.get(src));
@@ -75,51 +230,481 @@ impl Registers {
 
     // Steps backwards and indicates if any changes were undone.
     pub fn step_backward(&mut self) -> bool {
-        self.engine.step_backward().map_or(false, |changes| {
-            for Change(reg, val) in changes.rev() {
-                self.registers[reg as usize] = val
+        self.engine.step_backward().is_some_and(|changes| {
+            for Change(reg, previous, _) in changes.iter().rev() {
+                self.registers[*reg] = *previous
+            }
+            true
+        })
+    }
+
+    // Steps forward through a recorded step and indicates if any changes were redone.
+    pub fn redo(&mut self) -> bool {
+        self.engine.redo().is_some_and(|changes| {
+            for Change(reg, _, val) in changes {
+                self.registers[*reg] = *val
             }
             true
         })
     }
+
+    /// The registers written in the step from 'time_step' to the one after it.
+    pub fn changes(&self, time_step: usize) -> &[Change] {
+        self.engine.changes(time_step)
+    }
+
+    /// The current value of every register.
+    pub fn values(&self) -> [u32; REGISTER_COUNT] {
+        self.registers
+    }
+
+    /// Returns to the register values of another recorded step.
+    pub fn restore(&mut self, time_step: usize, values: [u32; REGISTER_COUNT]) {
+        self.engine.jump_to(time_step);
+        self.registers = values;
+    }
+
+    /// Forgets every step recorded after the current one.
+    pub fn truncate_future(&mut self) {
+        self.engine.truncate_future();
+    }
+
+    /// Discards the history of every step before the given one.
+    pub fn discard_before(&mut self, time_step: usize) {
+        self.engine.discard_before(time_step);
+    }
+
+    /// Indicates whether the registers hold every step from 'first_step' to 'last_step', see [`TimelessEngine::holds`].
+    /// Indicates whether the registers are of the given width and their history holds every step from 'first_step'
+    /// to 'last_step', with each value fitting its register.
+    pub fn holds(
+        &self,
+        first_step: usize,
+        time_step: usize,
+        last_step: usize,
+        width: WordWidth,
+    ) -> bool {
+        let fits = |reg: usize, val: u32| {
+            Register::from_index(reg).is_some_and(|reg| width.mask(reg, val) == val)
+        };
+        self.width == width
+            && self
+                .registers
+                .iter()
+                .enumerate()
+                .all(|(reg, val)| fits(reg, *val))
+            && self
+                .engine
+                .holds(first_step, time_step, last_step, REGISTER_COUNT, fits)
+    }
+
+    /// Undoes the changes made in the current, unfinished step.
+    pub fn rollback(&mut self) {
+        for Change(reg, previous, _) in self.engine.rollback().rev() {
+            self.registers[reg] = previous
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    #[error("The program of {0} words does not fit at {1:#x} in an address space of {2} words")]
+    DoesNotFit(usize, u32, usize),
+    #[error("The program of {0} words at {1:#x} overlaps a device")]
+    OverlapsDevice(usize, u32),
+}
+
+/// A full copy of the contents of memory at some step in time.
+#[derive(Serialize, Deserialize)]
+pub struct Image {
+    words: Vec<u32>,
+    // The state of each device, by the address it is attached at.
+    devices: HashMap<u32, Vec<u32>>,
 }
 
+/// The address space of the machine.
+///
+/// Attached devices are not part of a snapshot and must be attached again after one is restored.
+#[derive(Serialize, Deserialize)]
 pub struct Memory {
     underlying: Vec<u32>,
+    width: WordWidth,
+    base: u32,
+    program_len: usize,
+    #[serde(skip)]
+    devices: Vec<(u32, Box<dyn Device>)>,
+    // Whether each word was written by the loader, as part of the program or kept from before it was loaded.
+    loaded: Vec<bool>,
+    // The step in time each word was first written at by the program.
+    first_writes: Vec<Option<usize>>,
     engine: TimelessEngine,
 }
 
 impl Memory {
-    pub fn new(instructions: &[u32]) -> Self {
-        Memory {
-            underlying: instructions.to_vec(),
+    /// Creates the default address space with the program loaded at address 0.
+    pub fn new(instructions: &[u32]) -> Result<Self, LoadError> {
+        Memory::with_layout(instructions, 0, DEFAULT_MEMORY_SIZE, WordWidth::default())
+    }
+
+    /// Creates an address space of 'size' words with the program loaded at 'base', the rest is zero-filled.
+    ///
+    /// Every word is truncated to the data width of the machine.
+    pub fn with_layout(
+        instructions: &[u32],
+        base: u32,
+        size: usize,
+        width: WordWidth,
+    ) -> Result<Self, LoadError> {
+        let mut memory = Memory {
+            underlying: vec![0; size],
+            width,
+            base,
+            program_len: 0,
+            devices: Vec::new(),
+            loaded: vec![false; size],
+            first_writes: vec![None; size],
             engine: TimelessEngine::new(),
+        };
+        memory.load(instructions, base, false)?;
+        Ok(memory)
+    }
+
+    /// Zero-fills the address space and loads the program at the base, forgetting every step recorded.
+    ///
+    /// Attached devices keep their state, their history begins again from the first step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the program does not fit in the address space at the base or overlaps a device, which it does when
+    /// it was loaded there before.
+    pub fn reset(&mut self, instructions: &[u32]) {
+        if let Err(e) = self.load(instructions, self.base, false) {
+            panic!("{e}");
+        }
+    }
+
+    /// Loads the program at 'base', forgetting every step recorded. The rest of the address space is zero-filled,
+    /// or keeps its words when 'keep' is set, those which were written before counting as written by the loader.
+    ///
+    /// Attached devices keep their state, their history begins again from the first step. Nothing is changed
+    /// when the program does not fit in the address space at 'base' or overlaps a device.
+    pub fn load(&mut self, instructions: &[u32], base: u32, keep: bool) -> Result<(), LoadError> {
+        let size = self.size();
+        let start = base as usize;
+        let end = start + instructions.len();
+        if end > size {
+            return Err(LoadError::DoesNotFit(instructions.len(), base, size));
+        }
+        if self.devices.iter().any(|(base, device)| {
+            (*base as usize) < end && start < *base as usize + device.size() as usize
+        }) {
+            return Err(LoadError::OverlapsDevice(instructions.len(), base));
+        }
+
+        if keep {
+            let loaded = (0..size as u32)
+                .map(|address| self.device_at(address).is_none() && self.initialized(address))
+                .collect();
+            self.loaded = loaded;
+        } else {
+            self.underlying.fill(0);
+            self.loaded.fill(false);
+        }
+        for (word, instruction) in self.underlying[start..end].iter_mut().zip(instructions) {
+            *word = instruction & self.width.data_mask();
+        }
+        self.loaded[start..end].fill(true);
+        self.base = base;
+        self.program_len = instructions.len();
+        self.first_writes.fill(None);
+
+        self.engine = TimelessEngine::new();
+        for (_, device) in &mut self.devices {
+            device.state_mut().start_at(0);
+        }
+        Ok(())
+    }
+
+    /// Returns every attached device to its power-on state.
+    pub fn reset_devices(&mut self) {
+        for (_, device) in &mut self.devices {
+            device.state_mut().reset();
+        }
+    }
+
+    /// The number of words in the address space.
+    pub fn size(&self) -> usize {
+        self.underlying.len()
+    }
+
+    /// The address the program was loaded at.
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// The range of addresses occupied by the loaded program.
+    pub fn program_range(&self) -> std::ops::Range<u32> {
+        self.base..self.base + self.program_len as u32
+    }
+
+    /// Maps a device into the address space starting at 'base', reads and writes to its range are routed to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the device extends past the largest address, or overlaps the program or another device.
+    pub fn attach(&mut self, base: u32, mut device: Box<dyn Device>) {
+        let end = base.checked_add(device.size()).unwrap_or_else(|| {
+            panic!(
+                "{} at {base:#x} extends past the largest address",
+                device.name()
+            )
+        });
+        let range = base..end;
+        let overlaps =
+            |other: &std::ops::Range<u32>| range.start < other.end && other.start < range.end;
+
+        assert!(
+            !overlaps(&self.program_range()),
+            "{} at {base:#x} overlaps the program",
+            device.name()
+        );
+        assert!(
+            !self
+                .devices
+                .iter()
+                .any(|(start, other)| overlaps(&(*start..start + other.size()))),
+            "{} at {base:#x} overlaps another device",
+            device.name()
+        );
+
+        // The device's history begins now, it cannot be stepped back to before it was attached.
+        device.state_mut().start_at(self.engine.time_step);
+        self.devices.push((base, device));
+    }
+
+    /// A copy of every word in the address space and of the state of every device.
+    pub fn image(&self) -> Image {
+        Image {
+            words: self.underlying.clone(),
+            devices: self
+                .devices
+                .iter()
+                .map(|(base, device)| (*base, device.state().words().to_vec()))
+                .collect(),
+        }
+    }
+
+    /// Indicates whether memory holds every step from 'first_step' to 'last_step', see [`TimelessEngine::holds`], the
+    /// program and record of first writes fit the address space, and every word fits the given width.
+    pub fn holds(
+        &self,
+        first_step: usize,
+        time_step: usize,
+        last_step: usize,
+        width: WordWidth,
+    ) -> bool {
+        let fits = |_, word: u32| word & !width.data_mask() == 0;
+        self.width == width
+            && self.first_writes.len() == self.size()
+            && self.loaded.len() == self.size()
+            && self.base as usize + self.program_len <= self.size()
+            && self.underlying.iter().all(|word| fits(0, *word))
+            && self
+                .engine
+                .holds(first_step, time_step, last_step, self.size(), fits)
+    }
+
+    /// Indicates whether an image was taken of an address space of the same size, with every word fitting its width.
+    pub fn fits(&self, image: &Image) -> bool {
+        image.words.len() == self.size()
+            && image
+                .words
+                .iter()
+                .all(|word| word & !self.width.data_mask() == 0)
+    }
+
+    /// Returns to the contents of another recorded step.
+    ///
+    /// Devices are matched to their state in the image by the address they are attached at, those attached there
+    /// after the image was taken are moved through their own history instead.
+    pub fn restore(&mut self, time_step: usize, image: &Image) {
+        self.engine.jump_to(time_step);
+        self.underlying.copy_from_slice(&image.words);
+
+        for (base, device) in &mut self.devices {
+            match image.devices.get(base) {
+                Some(words) => device.state_mut().restore(time_step, words.clone()),
+                None => device.state_mut().seek(time_step),
+            }
+        }
+    }
+
+    /// Forgets every step recorded after the current one.
+    pub fn truncate_future(&mut self) {
+        self.engine.truncate_future();
+
+        for (_, device) in &mut self.devices {
+            device.state_mut().truncate_future();
+        }
+
+        let time_step = self.engine.time_step();
+        for first_write in &mut self.first_writes {
+            if first_write.is_some_and(|step| step >= time_step) {
+                *first_write = None;
+            }
+        }
+    }
+
+    /// Discards the history of every step before the given one.
+    pub fn discard_before(&mut self, time_step: usize) {
+        self.engine.discard_before(time_step);
+
+        for (_, device) in &mut self.devices {
+            device.state_mut().discard_before(time_step);
+        }
+    }
+
+    /// The words written in the step from 'time_step' to the one after it, devices aside.
+    pub fn changes(&self, time_step: usize) -> &[Change] {
+        self.engine.changes(time_step)
+    }
+
+    /// The attached devices along with the address each is mapped at.
+    pub fn devices(&self) -> impl Iterator<Item = (u32, &dyn Device)> {
+        self.devices
+            .iter()
+            .map(|(base, device)| (*base, device.as_ref()))
+    }
+
+    /// Finds the index of the device mapped at an address along with the offset into it.
+    fn device_at(&self, address: u32) -> Option<(usize, u32)> {
+        self.devices
+            .iter()
+            .position(|(base, device)| (*base..base + device.size()).contains(&address))
+            .map(|idx| (idx, address - self.devices[idx].0))
+    }
+
+    /// Retrieves the value at the given address without side effects, if it is within the address space.
+    pub fn get(&self, address: u32) -> Option<u32> {
+        match self.device_at(address) {
+            Some((idx, offset)) => Some(self.devices[idx].1.peek(offset) & self.width.data_mask()),
+            None => self.underlying.get(address as usize).copied(),
+        }
+    }
+
+    /// Reads the value at the given address as the CPU would, if it is within the address space.
+    pub fn read(&mut self, address: u32) -> Option<u32> {
+        match self.device_at(address) {
+            Some((idx, offset)) => Some(self.devices[idx].1.read(offset) & self.width.data_mask()),
+            None => self.get(address),
+        }
+    }
+
+    /// Sets the value at the given address, if it is within the address space.
+    pub fn set(&mut self, address: u32, val: u32) -> Option<()> {
+        let val = val & self.width.data_mask();
+
+        if let Some((idx, offset)) = self.device_at(address) {
+            self.devices[idx].1.write(offset, val);
+            return Some(());
         }
+
+        let slot = self.underlying.get_mut(address as usize)?;
+        self.engine.add_change(address as usize, *slot, val);
+        *slot = val;
+
+        self.first_writes[address as usize].get_or_insert(self.engine.time_step());
+        Some(())
     }
 
-    /// Retrieves the value at the given address.
-    pub fn get(&self, address: u32) -> u32 {
-        self.underlying[address as usize]
+    /// Indicates whether every word and the state of every device is as it was at the given step.
+    pub fn unchanged_since(&self, time_step: usize) -> bool {
+        self.engine.unchanged_since(time_step)
+            && self
+                .devices
+                .iter()
+                .all(|(_, device)| device.state().unchanged_since(time_step))
     }
 
-    /// Sets the value at the given address.
-    pub fn set(&mut self, address: u32, val: u32) {
-        self.engine.add_change(address as usize, val);
+    /// Indicates whether an address was written by the loader, as part of the program or kept from before it.
+    pub fn loaded(&self, address: u32) -> bool {
+        self.loaded.get(address as usize).copied().unwrap_or(false)
+    }
+
+    /// Indicates whether an address was written by the loader, belongs to a device or was written before the
+    /// current step.
+    pub fn initialized(&self, address: u32) -> bool {
+        self.loaded(address)
+            || self.device_at(address).is_some()
+            || self
+                .first_writes
+                .get(address as usize)
+                .copied()
+                .flatten()
+                .is_some_and(|step| step < self.engine.time_step())
+    }
+
+    /// Indicates whether any device is raising the interrupt request line.
+    pub fn interrupt_requested(&self) -> bool {
+        self.devices.iter().any(|(_, device)| device.interrupt())
+    }
 
-        self.underlying[address as usize] = val;
+    /// Advances every device by a micro-operation.
+    pub fn tick(&mut self) {
+        for (_, device) in &mut self.devices {
+            device.tick();
+        }
     }
 
     pub fn step_forward(&mut self) {
-        self.engine.step_forward()
+        self.engine.step_forward();
+
+        for (_, device) in &mut self.devices {
+            device.state_mut().step_forward();
+        }
     }
 
     // Steps backwards and indicates if any changes were undone.
     pub fn step_backward(&mut self) -> bool {
-        self.engine.step_backward().map_or(false, |changes| {
-            for Change(address, val) in changes.rev() {
-                self.underlying[address] = val;
+        // Devices attached later than the current step have nothing to undo yet.
+        for (_, device) in &mut self.devices {
+            if device.state().time_step() == self.engine.time_step() {
+                device.state_mut().step_backward();
+            }
+        }
+
+        self.engine.step_backward().is_some_and(|changes| {
+            for Change(address, previous, _) in changes.iter().rev() {
+                self.underlying[*address] = *previous;
+            }
+            true
+        })
+    }
+
+    // Steps forward through a recorded step and indicates if any changes were redone.
+    pub fn redo(&mut self) -> bool {
+        for (_, device) in &mut self.devices {
+            if device.state().time_step() == self.engine.time_step() {
+                device.state_mut().redo();
+            }
+        }
+
+        self.engine.redo().is_some_and(|changes| {
+            for Change(address, _, val) in changes {
+                self.underlying[*address] = *val;
             }
             true
         })
     }
+
+    /// Undoes the changes made in the current, unfinished step.
+    pub fn rollback(&mut self) {
+        for (_, device) in &mut self.devices {
+            device.state_mut().rollback();
+        }
+
+        for Change(address, previous, _) in self.engine.rollback().rev() {
+            self.underlying[address] = previous;
+        }
+    }
 }
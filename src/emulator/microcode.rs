@@ -0,0 +1,546 @@
+use super::{
+    config::WordWidth,
+    datapath::{ControlSignal, Datapath},
+    isa::{Context, Isa, Operation},
+    observer::Notification,
+    util::{Fault, MicroState, Register},
+    Emulator,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use Register as R;
+
+/// The address of the micro-instruction every instruction fetch begins at.
+pub const FETCH_ADDRESS: usize = 0;
+
+/// The most micro-instructions a single instruction may take, a micro-routine that runs longer is taken to never end.
+pub const MICRO_STEP_LIMIT: usize = 1 << 16;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("The microcode has no micro-routine named {1} for {0}")]
+    MissingRoutine(&'static str, &'static str),
+}
+
+/// The micro-instruction that follows one of the hardwired states, while laying out the ROM.
+enum To {
+    State(MicroState),
+    Map,
+    End,
+}
+
+/// A control signal asserted by a micro-instruction, each performing a single register transfer.
+///
+/// Each stands for some of the control signals of the datapath, given by [`Signal::control_signals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Signal {
+    PcToAr,
+    /// Reads the word at AR into DR.
+    Read,
+    /// Writes DR to the word at AR.
+    Write,
+    IncPc,
+    IncAr,
+    DrToIr,
+    DrToTr,
+    /// Moves the operand address held in DR, and TR when operands take two words, into AR.
+    OperandToAr,
+    /// Moves the operand address held in DR, and TR when operands take two words, into PC.
+    OperandToPc,
+    DrToAcc,
+    AccToDr,
+    AccToR,
+    RToAcc,
+    AccToOutr,
+    Add,
+    Sub,
+    Inc,
+    Clear,
+    And,
+    Or,
+    ShiftRight,
+    Not,
+    Halt,
+    /// Part of the interrupt extension.
+    EnableInterrupts,
+    /// Part of the interrupt extension.
+    DisableInterrupts,
+    /// Saves PC in EPC, jumps to the interrupt vector and disables interrupts, part of the interrupt extension.
+    EnterInterrupt,
+    /// Part of the interrupt extension.
+    EpcToPc,
+    /// The register transfers of the custom instruction in IR, given its operand if it takes one.
+    Custom,
+}
+
+impl Signal {
+    /// The register transfer performed by the signal for the given word width, in RTL notation.
+    pub fn rtl(self, width: WordWidth) -> &'static str {
+        let textbook = width == WordWidth::Textbook;
+
+        match self {
+            Self::PcToAr => "AR <- PC",
+            Self::Read => "DR <- M",
+            Self::Write => "M <- DR",
+            Self::IncPc => "PC <- PC + 1",
+            Self::IncAr => "AR <- AR + 1",
+            Self::DrToIr => "IR <- DR",
+            Self::DrToTr => "TR <- DR",
+            Self::OperandToAr if textbook => "AR <- DR,TR",
+            Self::OperandToAr => "AR <- DR",
+            Self::OperandToPc if textbook => "PC <- DR,TR",
+            Self::OperandToPc => "PC <- DR",
+            Self::DrToAcc => "ACC <- DR",
+            Self::AccToDr => "DR <- ACC",
+            Self::AccToR => "R <- ACC",
+            Self::RToAcc => "ACC <- R",
+            Self::AccToOutr => "OUTR <- ACC",
+            Self::Add => "ACC <- ACC + R",
+            Self::Sub => "ACC <- ACC - R",
+            Self::Inc => "ACC <- ACC + 1",
+            Self::Clear => "ACC <- 0",
+            Self::And => "ACC <- ACC & R",
+            Self::Or => "ACC <- ACC | R",
+            Self::ShiftRight => "ACC <- ACC >> 1",
+            Self::Not => "ACC <- !ACC",
+            Self::Halt => "S <- 1",
+            Self::EnableInterrupts => "IE <- 1",
+            Self::DisableInterrupts => "IE <- 0",
+            Self::EnterInterrupt => "EPC <- PC, PC <- vector, IE <- 0",
+            Self::EpcToPc => "PC <- EPC",
+            Self::Custom => "custom",
+        }
+    }
+
+    /// The control signals of the datapath asserted by the signal, which depend on the word width and on whether
+    /// the extended flags are enabled. Those of custom instructions are not known.
+    pub fn control_signals(self, width: WordWidth, extended_flags: bool) -> Vec<ControlSignal> {
+        use ControlSignal as C;
+
+        let flags: &[C] = if extended_flags {
+            &[C::CLoad, C::VLoad, C::NLoad]
+        } else {
+            &[]
+        };
+        let operand: &[C] = match width {
+            WordWidth::Textbook => &[C::DrBus, C::TrBus],
+            WordWidth::Wide => &[C::DrBus],
+        };
+
+        let signals: &[&[C]] = match self {
+            Self::PcToAr => &[&[C::PcBus, C::ArLoad]],
+            Self::Read => &[&[C::Read, C::MemBus, C::DrLoad]],
+            Self::Write => &[&[C::DrBus, C::BusMem, C::Write]],
+            Self::IncPc => &[&[C::PcInc]],
+            Self::IncAr => &[&[C::ArInc]],
+            Self::DrToIr => &[&[C::IrLoad]],
+            Self::DrToTr => &[&[C::TrLoad]],
+            Self::OperandToAr => &[operand, &[C::ArLoad]],
+            Self::OperandToPc => &[operand, &[C::PcLoad]],
+            Self::DrToAcc => &[&[C::DrBus, C::AluPass, C::AcLoad]],
+            Self::AccToDr => &[&[C::AcBus, C::DrLoad]],
+            Self::AccToR => &[&[C::AcBus, C::RLoad]],
+            Self::RToAcc => &[&[C::RBus, C::AluPass, C::AcLoad]],
+            Self::AccToOutr => &[&[C::AcBus, C::OutrLoad]],
+            Self::Add => &[&[C::RBus, C::AluAdd, C::AcLoad], flags],
+            Self::Sub => &[&[C::RBus, C::AluSub, C::AcLoad], flags],
+            Self::Inc => &[&[C::AluInc, C::AcLoad], flags],
+            Self::Clear => &[&[C::AluClear, C::AcLoad]],
+            Self::And => &[&[C::RBus, C::AluAnd, C::AcLoad]],
+            Self::Or => &[&[C::RBus, C::AluOr, C::AcLoad]],
+            Self::ShiftRight => &[&[C::AluShiftRight, C::AcLoad], flags],
+            Self::Not => &[&[C::AluNot, C::AcLoad]],
+            Self::Halt => &[&[C::SLoad]],
+            Self::EnableInterrupts | Self::DisableInterrupts => &[&[C::IeLoad]],
+            Self::EnterInterrupt => &[&[C::PcBus, C::EpcLoad, C::PcLoad, C::IeLoad]],
+            Self::EpcToPc => &[&[C::PcLoad]],
+            Self::Custom => &[],
+        };
+        signals.concat()
+    }
+}
+
+/// How the micro-sequencer picks the micro-instruction that follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Next {
+    Goto(usize),
+    /// The first address when the flag is set, the second otherwise.
+    Branch(Register, usize, usize),
+    /// The micro-routine of the opcode in IR, as given by the mapping ROM.
+    Map,
+    /// Ends the instruction, entering an interrupt when one is requested and fetching the next instruction otherwise.
+    End,
+}
+
+/// A single word of the microcode ROM.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MicroInstruction {
+    /// The label of the micro-instruction for display.
+    pub name: String,
+    /// Performed in order, each seeing the transfers of those before it.
+    pub signals: Vec<Signal>,
+    pub next: Next,
+}
+
+impl MicroInstruction {
+    /// The register transfers performed by the micro-instruction, in RTL notation.
+    pub fn rtl(&self, width: WordWidth) -> String {
+        self.signals
+            .iter()
+            .map(|signal| signal.rtl(width))
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+
+    /// The control signals of the datapath asserted by the micro-instruction, in the order of
+    /// [`ControlSignal::iter`]. Ending an instruction loads Z.
+    pub fn control_signals(&self, width: WordWidth, extended_flags: bool) -> Vec<ControlSignal> {
+        let mut datapath = Datapath::default();
+        for signal in &self.signals {
+            for control_signal in signal.control_signals(width, extended_flags) {
+                datapath.assert(control_signal);
+            }
+        }
+        if self.next == Next::End {
+            datapath.assert(ControlSignal::ZLoad);
+        }
+
+        datapath.signals().collect()
+    }
+}
+
+/// The ROM of a microprogrammed control unit, along with its mapping ROM.
+///
+/// Every micro-instruction takes a single clock cycle. The ROM can be loaded from JSON, the textbook RSC's is
+/// given by [`Microcode::for_isa`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Microcode {
+    /// The micro-instructions, fetching an instruction begins at [`FETCH_ADDRESS`].
+    pub rom: Vec<MicroInstruction>,
+    /// Where the micro-routine of each opcode begins, either an address or a branch on a flag.
+    pub mapping: BTreeMap<u32, Next>,
+    /// The address of the micro-routine entering an interrupt, part of the interrupt extension.
+    pub interrupt: Option<usize>,
+}
+
+impl Microcode {
+    /// The microcode performing the same register transfers as the hardwired control unit.
+    ///
+    /// Micro-instructions are named after the states of the hardwired control unit they stand in for.
+    pub fn for_isa(isa: &Isa, width: WordWidth) -> Self {
+        use MicroState as M;
+        use Signal::*;
+        use To::{End, Map, State};
+
+        let textbook = width == WordWidth::Textbook;
+        let inc_ar = |signals: &[Signal]| -> Vec<Signal> {
+            let mut signals = signals.to_vec();
+            if textbook {
+                signals.push(IncAr);
+            }
+            signals
+        };
+        // Goes on to the state reading the high byte of an operand, when operands take two words.
+        let high = |high: M, done: M| if textbook { high } else { done };
+
+        let routines: Vec<(M, Vec<Signal>, To)> = vec![
+            (M::FETCH1, vec![PcToAr], State(M::FETCH2)),
+            (M::FETCH2, vec![Read, IncPc], State(M::FETCH3)),
+            (M::FETCH3, vec![DrToIr, PcToAr], Map),
+            (
+                M::LDAC1,
+                inc_ar(&[Read, IncPc]),
+                State(high(M::LDAC2, M::LDAC3)),
+            ),
+            (M::LDAC2, vec![DrToTr, Read, IncPc], State(M::LDAC3)),
+            (M::LDAC3, vec![OperandToAr], State(M::LDAC4)),
+            (M::LDAC4, vec![Read], State(M::LDAC5)),
+            (M::LDAC5, vec![DrToAcc], End),
+            (
+                M::STAC1,
+                inc_ar(&[Read, IncPc]),
+                State(high(M::STAC2, M::STAC3)),
+            ),
+            (M::STAC2, vec![DrToTr, Read, IncPc], State(M::STAC3)),
+            (M::STAC3, vec![OperandToAr], State(M::STAC4)),
+            (M::STAC4, vec![AccToDr], State(M::STAC5)),
+            (M::STAC5, vec![Write], End),
+            (M::MVAC1, vec![AccToR], End),
+            (M::MOVR1, vec![RToAcc], End),
+            (M::JMP1, inc_ar(&[Read]), State(high(M::JMP2, M::JMP3))),
+            (M::JMP2, vec![DrToTr, Read], State(M::JMP3)),
+            (M::JMP3, vec![OperandToPc], End),
+            (
+                M::JMPZY1,
+                inc_ar(&[Read]),
+                State(high(M::JMPZY2, M::JMPZY3)),
+            ),
+            (M::JMPZY2, vec![DrToTr, Read], State(M::JMPZY3)),
+            (M::JMPZY3, vec![OperandToPc], End),
+            (
+                M::JMPZN1,
+                vec![IncPc],
+                if textbook { State(M::JMPZN2) } else { End },
+            ),
+            (M::JMPZN2, vec![IncPc], End),
+            (M::OUT1, vec![AccToOutr], End),
+            (M::SUB1, vec![Sub], End),
+            (M::ADD1, vec![Add], End),
+            (M::INC1, vec![Inc], End),
+            (M::CLAC1, vec![Clear], End),
+            (M::AND1, vec![And], End),
+            (M::OR1, vec![Or], End),
+            (M::ASHR1, vec![ShiftRight], End),
+            (M::NOT1, vec![Not], End),
+            (M::HALT1, vec![Halt], End),
+            (M::INT1, vec![EnterInterrupt], State(M::FETCH1)),
+            (M::EI1, vec![EnableInterrupts], End),
+            (M::DI1, vec![DisableInterrupts], End),
+            (M::RETI1, vec![EpcToPc, EnableInterrupts], End),
+            (M::EXEC1, vec![Custom], End),
+            (
+                M::IMM1,
+                inc_ar(&[Read, IncPc]),
+                State(high(M::IMM2, M::IMM3)),
+            ),
+            (M::IMM2, vec![DrToTr, Read, IncPc], State(M::IMM3)),
+            (M::IMM3, vec![Custom], End),
+        ];
+
+        // The states handling the high byte of an operand are left out when operands take a single word.
+        let routines: Vec<_> = routines
+            .into_iter()
+            .filter(|(state, ..)| {
+                textbook
+                    || !matches!(
+                        state,
+                        M::LDAC2 | M::STAC2 | M::JMP2 | M::JMPZY2 | M::JMPZN2 | M::IMM2
+                    )
+            })
+            .collect();
+        let address = |state: M| {
+            routines
+                .iter()
+                .position(|(s, ..)| *s == state)
+                .expect("every state gone to is in the ROM")
+        };
+
+        let rom = routines
+            .iter()
+            .map(|(state, signals, to)| MicroInstruction {
+                name: state.as_str().to_string(),
+                signals: signals.clone(),
+                next: match to {
+                    To::State(state) => Next::Goto(address(*state)),
+                    To::Map => Next::Map,
+                    To::End => Next::End,
+                },
+            })
+            .collect();
+
+        let mut microcode = Microcode {
+            rom,
+            mapping: BTreeMap::new(),
+            interrupt: Some(address(M::INT1)),
+        };
+        microcode
+            .map(isa)
+            .expect("every instruction begins at a state in the ROM");
+        microcode
+    }
+
+    /// Rebuilds the mapping ROM for the opcodes of an instruction set.
+    ///
+    /// The micro-routine of each instruction is the one named after the first state of the instruction in the
+    /// hardwired control unit. The mapping is left as it was when one is missing.
+    pub fn map(&mut self, isa: &Isa) -> Result<(), Error> {
+        let address = |mnemonic: &'static str, state: MicroState| {
+            self.rom
+                .iter()
+                .position(|instruction| instruction.name == state.as_str())
+                .ok_or(Error::MissingRoutine(mnemonic, state.as_str()))
+        };
+
+        let mapping = isa
+            .iter()
+            .map(|instruction| {
+                let operation = instruction.operation;
+                let first = |taken| address(instruction.mnemonic, operation.first_state(taken));
+                let next = match operation.condition() {
+                    Some(flag) => Next::Branch(flag, first(true)?, first(false)?),
+                    None => Next::Goto(first(false)?),
+                };
+                Ok((instruction.opcode, next))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        self.mapping = mapping;
+        Ok(())
+    }
+
+    /// Loads microcode written as JSON, in the form it is saved by [`Microcode::to_json`].
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Emulator {
+    /// The microcode driving the control unit, when it is microprogrammed.
+    pub fn microcode(&self) -> Option<&Microcode> {
+        self.microcode.as_ref()
+    }
+
+    /// Switches to a microprogrammed control unit driven by the given microcode, or back to the hardwired one,
+    /// giving whether it switched.
+    ///
+    /// Either control unit begins by fetching the next instruction, so nothing is switched unless one is about to
+    /// be fetched. The steps before the switch were taken by the other control unit and can no longer be returned
+    /// to, the steps after it are forgotten.
+    pub fn set_microcode(&mut self, microcode: Option<Microcode>) -> bool {
+        let fetching = match self.microcode {
+            Some(_) => self.micro_pc == FETCH_ADDRESS,
+            None => self.state == MicroState::FETCH1,
+        };
+        if !fetching {
+            return false;
+        }
+
+        self.truncate_future();
+        self.discard_before(self.time_step);
+        self.microcode = microcode;
+        self.micro_pc = FETCH_ADDRESS;
+        self.state = MicroState::FETCH1;
+        self.trace[0] = self.control();
+        if self.history.record {
+            self.checkpoint();
+        }
+        true
+    }
+
+    /// The address of the micro-instruction to be performed next, when the control unit is microprogrammed.
+    pub fn micro_pc(&self) -> Option<usize> {
+        self.microcode.as_ref().map(|_| self.micro_pc)
+    }
+
+    /// The micro-instruction to be performed next, when the control unit is microprogrammed.
+    pub fn micro_instruction(&self) -> Option<&MicroInstruction> {
+        self.microcode.as_ref()?.rom.get(self.micro_pc)
+    }
+
+    /// Performs the micro-instruction at the micro-PC and moves the micro-PC on to the one that follows.
+    pub(super) fn perform_micro_instruction(&mut self) -> Result<(), Fault> {
+        let micro_pc = self.micro_pc;
+        let next = self
+            .micro_instruction()
+            .map(|instruction| instruction.next)
+            .ok_or(Fault::MicroAddressOutOfRange(
+                self.instruction_address,
+                micro_pc,
+            ))?;
+
+        if micro_pc == FETCH_ADDRESS {
+            self.begin_instruction()?;
+        }
+        // The signals are looked up one at a time, as asserting them needs the emulator.
+        let mut idx = 0;
+        while let Some(&signal) = self
+            .microcode
+            .as_ref()
+            .and_then(|microcode| microcode.rom[micro_pc].signals.get(idx))
+        {
+            self.assert(signal)?;
+            idx += 1;
+        }
+
+        self.micro_pc = self.sequence(next)?;
+        Ok(())
+    }
+
+    /// Determines the address of the micro-instruction that follows.
+    fn sequence(&mut self, next: Next) -> Result<usize, Fault> {
+        let address = match next {
+            Next::Goto(address) => address,
+            Next::Branch(flag, set, _) if self.registers.get(flag) == 1 => set,
+            Next::Branch(_, _, clear) => clear,
+            Next::Map => {
+                let opcode = self.registers.get(R::IR);
+                let instruction = self
+                    .isa
+                    .decode(opcode)
+                    .filter(|i| self.enabled(i))
+                    .ok_or(self.illegal_opcode())?;
+                let next = self
+                    .microcode
+                    .as_ref()
+                    .and_then(|microcode| microcode.mapping.get(&opcode))
+                    .copied()
+                    .filter(|next| *next != Next::Map)
+                    .ok_or(self.illegal_opcode())?;
+
+                let address = self.instruction_address;
+                self.notify(Notification::Fetch(address, instruction));
+                return self.sequence(next);
+            }
+            Next::End => {
+                self.update_z();
+                match self.microcode.as_ref().and_then(|m| m.interrupt) {
+                    Some(interrupt) if self.interrupt_requested() => interrupt,
+                    _ => FETCH_ADDRESS,
+                }
+            }
+        };
+
+        Ok(address)
+    }
+
+    /// Performs the register transfer of a control signal.
+    fn assert(&mut self, signal: Signal) -> Result<(), Fault> {
+        use Signal::*;
+
+        match signal {
+            PcToAr => self.transfer(R::PC, R::AR),
+            Read => self.load_dr()?,
+            Write => self.stac()?,
+            IncPc => self.inc_pc(),
+            IncAr => self.inc_ar(),
+            DrToIr => self.transfer(R::DR, R::IR),
+            DrToTr => self.transfer(R::DR, R::TR),
+            OperandToAr => self.operand_to(R::AR),
+            OperandToPc => self.operand_to(R::PC),
+            DrToAcc => self.transfer(R::DR, R::ACC),
+            AccToDr => self.transfer(R::ACC, R::DR),
+            AccToR => self.mvac(),
+            RToAcc => self.movr(),
+            AccToOutr => self.out(),
+            Add => self.add(),
+            Sub => self.sub(),
+            Inc => self.inc(),
+            Clear => self.clac(),
+            And => self.and(),
+            Or => self.or(),
+            ShiftRight => self.ashr(),
+            Not => self.not(),
+            Halt => self.halt(),
+            EnableInterrupts => self.set(R::IE, 1),
+            DisableInterrupts => self.set(R::IE, 0),
+            EnterInterrupt => self.enter_interrupt(),
+            EpcToPc => self.transfer(R::EPC, R::PC),
+            Custom => match self.operation() {
+                Some(Operation::Custom(operation)) => operation(&mut Context::new(self))?,
+                Some(Operation::CustomWithOperand(operation)) => {
+                    let operand = self.operand();
+                    operation(&mut Context::new(self), operand)?
+                }
+                _ => return Err(self.illegal_opcode()),
+            },
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use super::{isa::Instruction, util::Register};
+
+/// Watches the execution of an emulator, every method does nothing unless overridden.
+///
+/// Observers are told about a micro-operation once it has completed, never about one which faulted and was undone,
+/// nor about steps undone or redone when moving through the timeline. When cycling through a whole instruction, they
+/// are told once all of it has completed, and nothing of an instruction which faulted.
+pub trait Observer {
+    /// An instruction was fetched from 'address' and decoded.
+    fn fetch(&mut self, _address: u32, _instruction: Instruction) {}
+
+    /// A register was set.
+    fn register_set(&mut self, _reg: Register, _val: u32) {}
+
+    /// A word was read from memory or a device.
+    fn memory_read(&mut self, _address: u32, _val: u32) {}
+
+    /// A word was written to memory or a device.
+    fn memory_write(&mut self, _address: u32, _val: u32) {}
+
+    /// A value was written through OUT.
+    fn output(&mut self, _val: u32) {}
+
+    /// The machine halted.
+    fn halt(&mut self) {}
+}
+
+/// Identifies an observer added to an emulator, for removing it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub(super) usize);
+
+/// Something observers are told about, held until the micro-operation it happened in has completed.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Notification {
+    Fetch(u32, Instruction),
+    RegisterSet(Register, u32),
+    MemoryRead(u32, u32),
+    MemoryWrite(u32, u32),
+    Output(u32),
+    Halt,
+}
+
+impl Notification {
+    pub(super) fn send(self, observer: &mut dyn Observer) {
+        match self {
+            Self::Fetch(address, instruction) => observer.fetch(address, instruction),
+            Self::RegisterSet(reg, val) => observer.register_set(reg, val),
+            Self::MemoryRead(address, val) => observer.memory_read(address, val),
+            Self::MemoryWrite(address, val) => observer.memory_write(address, val),
+            Self::Output(val) => observer.output(val),
+            Self::Halt => observer.halt(),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::assembler::Assembler;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How often a single address, or a line of source, has been used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    /// Instructions fetched and decoded at the address.
+    pub executions: u64,
+    /// Reads of the address, including those of instruction fetches and operands.
+    pub reads: u64,
+    pub writes: u64,
+    /// Executions of a JMPZ at the address which jumped.
+    pub taken: u64,
+    /// Executions of a JMPZ at the address which fell through.
+    pub not_taken: u64,
+}
+
+impl Counts {
+    /// Indicates whether the address was never executed, read or written.
+    pub fn untouched(&self) -> bool {
+        *self == Counts::default()
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.executions += other.executions;
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.taken += other.taken;
+        self.not_taken += other.not_taken;
+    }
+}
+
+/// What a single micro-operation counts towards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Event {
+    /// An instruction was executed at the address, a JMPZ along with whether it was taken.
+    Executed(u32, Option<bool>),
+    Read(u32),
+    Written(u32),
+}
+
+/// Per-address counters of the steps leading up to the current one.
+///
+/// The counters follow the timeline, stepping backward takes back what the undone steps counted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    counts: BTreeMap<u32, Counts>,
+    instructions: u64,
+}
+
+impl Profile {
+    /// The counters of an address.
+    pub fn get(&self, address: u32) -> Counts {
+        self.counts.get(&address).copied().unwrap_or_default()
+    }
+
+    /// The number of instructions executed.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Every address that has been used along with its counters, lowest address first.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Counts)> + '_ {
+        self.counts
+            .iter()
+            .map(|(address, counts)| (*address, *counts))
+    }
+
+    /// The addresses of executed instructions, most executed first.
+    pub fn hot_spots(&self) -> Vec<(u32, u64)> {
+        let mut spots: Vec<(u32, u64)> = self
+            .iter()
+            .filter(|(_, counts)| counts.executions > 0)
+            .map(|(address, counts)| (address, counts.executions))
+            .collect();
+        spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        spots
+    }
+
+    /// The counters of every line of source that assembled to something, summed over the words of the line.
+    pub fn lines(&self, assembler: &Assembler) -> BTreeMap<usize, Counts> {
+        assembler
+            .line_map
+            .iter()
+            .map(|(&line, &(start, end))| {
+                let mut counts = Counts::default();
+                for idx in start..=end {
+                    counts.add(&self.get(assembler.origin + idx as u32));
+                }
+                (line, counts)
+            })
+            .collect()
+    }
+
+    /// The lines of source whose words were never executed, read or written.
+    pub fn untouched_lines(&self, assembler: &Assembler) -> Vec<usize> {
+        self.lines(assembler)
+            .into_iter()
+            .filter(|(_, counts)| counts.untouched())
+            .map(|(line, _)| line)
+            .collect()
+    }
+
+    pub(super) fn count(&mut self, event: Event) {
+        self.update(event, |n| *n += 1);
+    }
+
+    /// Takes back what an event counted, when the step it came from is undone.
+    pub(super) fn uncount(&mut self, event: Event) {
+        self.update(event, |n| *n -= 1);
+    }
+
+    fn update(&mut self, event: Event, change: impl Fn(&mut u64)) {
+        let (Event::Executed(address, _) | Event::Read(address) | Event::Written(address)) = event;
+        let counts = self.counts.entry(address).or_default();
+
+        match event {
+            Event::Executed(_, taken) => {
+                change(&mut self.instructions);
+                change(&mut counts.executions);
+                match taken {
+                    Some(true) => change(&mut counts.taken),
+                    Some(false) => change(&mut counts.not_taken),
+                    None => (),
+                }
+            }
+            Event::Read(_) => change(&mut counts.reads),
+            Event::Written(_) => change(&mut counts.writes),
+        }
+
+        if counts.untouched() {
+            self.counts.remove(&address);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use super::{config::WordWidth, microcode::MICRO_STEP_LIMIT};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,22 +14,68 @@ pub enum Error {
     UndefinedVariable(String, usize),
     #[error("An attempt to redefine '{0}' occurred on line {1}")]
     Redefinition(String, usize),
+    #[error("The initializer of '{0}' on line {1} does not fit in a word")]
+    InitializerOutOfRange(String, usize),
+}
+
+/// Faults raised during execution, each carrying the address of the faulting instruction.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fault {
+    #[error("An illegal opcode {1:#x} was fetched at {0:#x}")]
+    IllegalOpcode(u32, u32),
+    #[error("An access to address {1:#x} was out of range at {0:#x}")]
+    AddressOutOfRange(u32, u32),
+    #[error("An instruction was fetched from {0:#x}, which was not loaded")]
+    FetchOutOfProgram(u32),
+    #[error("An instruction was fetched from data at {0:#x}")]
+    ExecuteData(u32),
+    #[error("A store to address {1:#x} overwrote an executed instruction at {0:#x}")]
+    SelfModifyingCode(u32, u32),
+    #[error("Address {1:#x} was read before it was ever written at {0:#x}")]
+    UninitializedRead(u32, u32),
+    #[error("The micro-PC {1} is outside of the microcode ROM at {0:#x}")]
+    MicroAddressOutOfRange(u32, usize),
+    #[error("The instruction at {0:#x} did not end within {MICRO_STEP_LIMIT} micro-instructions")]
+    MicroStepLimit(u32),
+}
+
+/// Whether a word of the program holds code or data, as laid out by the assembler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WordKind {
+    /// An opcode or one of its operand words.
+    Code,
+    /// A variable's initializer.
+    Data,
 }
 
 /// All registers in the RSC architecture.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Register {
     S,
     Z,
     IR,
     AR,
     DR,
+    TR,
     PC,
     OUTR,
     ACC,
     R,
+    /// The interrupt enable flag, part of the interrupt extension.
+    IE,
+    /// The address an interrupt returns to, part of the interrupt extension.
+    EPC,
+    /// The carry flag, part of the extended flags. Set when an addition carries out of ACC or a subtraction borrows.
+    C,
+    /// The overflow flag, part of the extended flags. Set when a signed result does not fit in ACC.
+    V,
+    /// The negative flag, part of the extended flags. A copy of the sign bit of ACC.
+    N,
 }
 
+/// The number of registers, those of the interrupt extension and extended flags included.
+pub const REGISTER_COUNT: usize = 15;
+
 impl Register {
     pub fn as_str(self) -> &'static str {
         match self {
@@ -36,13 +84,24 @@ impl Register {
             Self::IR => "IR",
             Self::AR => "AR",
             Self::DR => "DR",
+            Self::TR => "TR",
             Self::PC => "PC",
             Self::OUTR => "OUTR",
             Self::ACC => "ACC",
             Self::R => "R",
+            Self::IE => "IE",
+            Self::EPC => "EPC",
+            Self::C => "C",
+            Self::V => "V",
+            Self::N => "N",
         }
     }
 
+    /// The register with the given index, as registers are indexed by [`Register`] in arrays of their values.
+    pub fn from_index(idx: usize) -> Option<Register> {
+        Register::iter().copied().find(|reg| *reg as usize == idx)
+    }
+
     pub fn iter() -> std::slice::Iter<'static, Register> {
         [
             Register::S,
@@ -50,88 +109,167 @@ impl Register {
             Register::IR,
             Register::AR,
             Register::DR,
+            Register::TR,
             Register::PC,
             Register::OUTR,
             Register::ACC,
             Register::R,
+            Register::IE,
+            Register::EPC,
+            Register::C,
+            Register::V,
+            Register::N,
         ]
         .iter()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-/// All instructions in the RSC architecture.
-pub enum Instruction {
-    HALT,
-    LDAC,
-    STAC,
-    MVAC,
-    MOVR,
-    JMP,
-    JMPZ,
-    OUT,
-    SUB,
-    ADD,
-    INC,
-    CLAC,
-    AND,
-    OR,
-    ASHR,
-    NOT,
-}
-
-impl Instruction {
-    pub fn has_operand(&self) -> bool {
-        matches!(self, Self::LDAC | Self::STAC | Self::JMP | Self::JMPZ)
-    }
+/// The register transfer states the RSC control unit steps through, named as in the textbook.
+///
+/// States which handle the high byte of an operand address are skipped when operands take a single word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MicroState {
+    FETCH1,
+    FETCH2,
+    FETCH3,
+    LDAC1,
+    LDAC2,
+    LDAC3,
+    LDAC4,
+    LDAC5,
+    STAC1,
+    STAC2,
+    STAC3,
+    STAC4,
+    STAC5,
+    MVAC1,
+    MOVR1,
+    JMP1,
+    JMP2,
+    JMP3,
+    /// Also the first state of the other conditional jumps when their condition holds.
+    JMPZY1,
+    JMPZY2,
+    JMPZY3,
+    /// Also the first state of the other conditional jumps when their condition does not hold.
+    JMPZN1,
+    JMPZN2,
+    OUT1,
+    SUB1,
+    ADD1,
+    INC1,
+    CLAC1,
+    AND1,
+    OR1,
+    ASHR1,
+    NOT1,
+    HALT1,
+    /// Entry to an interrupt between instructions, part of the interrupt extension.
+    INT1,
+    EI1,
+    DI1,
+    RETI1,
+    /// The register transfers of a custom instruction without an operand.
+    EXEC1,
+    /// Reads the operand of a custom instruction, as LDAC1.
+    IMM1,
+    /// Reads the high byte of the operand of a custom instruction, as LDAC2.
+    IMM2,
+    /// The register transfers of a custom instruction given its operand.
+    IMM3,
 }
 
-impl TryFrom<&str> for Instruction {
-    type Error = ();
-
-    fn try_from(s: &str) -> Result<Self, Self::Error> {
-        match s {
-            "HALT" => Ok(Self::HALT),
-            "LDAC" => Ok(Self::LDAC),
-            "STAC" => Ok(Self::STAC),
-            "MVAC" => Ok(Self::MVAC),
-            "MOVR" => Ok(Self::MOVR),
-            "JMP" => Ok(Self::JMP),
-            "JMPZ" => Ok(Self::JMPZ),
-            "OUT" => Ok(Self::OUT),
-            "SUB" => Ok(Self::SUB),
-            "ADD" => Ok(Self::ADD),
-            "INC" => Ok(Self::INC),
-            "CLAC" => Ok(Self::CLAC),
-            "AND" => Ok(Self::AND),
-            "OR" => Ok(Self::OR),
-            "ASHR" => Ok(Self::ASHR),
-            "NOT" => Ok(Self::NOT),
-            _ => Err(()),
+impl MicroState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FETCH1 => "FETCH1",
+            Self::FETCH2 => "FETCH2",
+            Self::FETCH3 => "FETCH3",
+            Self::LDAC1 => "LDAC1",
+            Self::LDAC2 => "LDAC2",
+            Self::LDAC3 => "LDAC3",
+            Self::LDAC4 => "LDAC4",
+            Self::LDAC5 => "LDAC5",
+            Self::STAC1 => "STAC1",
+            Self::STAC2 => "STAC2",
+            Self::STAC3 => "STAC3",
+            Self::STAC4 => "STAC4",
+            Self::STAC5 => "STAC5",
+            Self::MVAC1 => "MVAC1",
+            Self::MOVR1 => "MOVR1",
+            Self::JMP1 => "JMP1",
+            Self::JMP2 => "JMP2",
+            Self::JMP3 => "JMP3",
+            Self::JMPZY1 => "JMPZY1",
+            Self::JMPZY2 => "JMPZY2",
+            Self::JMPZY3 => "JMPZY3",
+            Self::JMPZN1 => "JMPZN1",
+            Self::JMPZN2 => "JMPZN2",
+            Self::OUT1 => "OUT1",
+            Self::SUB1 => "SUB1",
+            Self::ADD1 => "ADD1",
+            Self::INC1 => "INC1",
+            Self::CLAC1 => "CLAC1",
+            Self::AND1 => "AND1",
+            Self::OR1 => "OR1",
+            Self::ASHR1 => "ASHR1",
+            Self::NOT1 => "NOT1",
+            Self::HALT1 => "HALT1",
+            Self::INT1 => "INT1",
+            Self::EI1 => "EI1",
+            Self::DI1 => "DI1",
+            Self::RETI1 => "RETI1",
+            Self::EXEC1 => "EXEC1",
+            Self::IMM1 => "IMM1",
+            Self::IMM2 => "IMM2",
+            Self::IMM3 => "IMM3",
         }
     }
-}
 
-impl From<u32> for Instruction {
-    fn from(value: u32) -> Self {
-        match value {
-            0 => Self::HALT,
-            1 => Self::LDAC,
-            2 => Self::STAC,
-            3 => Self::MVAC,
-            4 => Self::MOVR,
-            5 => Self::JMP,
-            6 => Self::JMPZ,
-            7 => Self::OUT,
-            8 => Self::SUB,
-            9 => Self::ADD,
-            10 => Self::INC,
-            11 => Self::CLAC,
-            12 => Self::AND,
-            13 => Self::OR,
-            14 => Self::ASHR,
-            15 => Self::NOT,
-            _ => unreachable!(),
+    /// The register transfers performed in this state for the given word width, in RTL notation.
+    pub fn rtl(self, width: WordWidth) -> &'static str {
+        let textbook = width == WordWidth::Textbook;
+
+        match self {
+            Self::FETCH1 => "AR <- PC",
+            Self::FETCH2 => "DR <- M, PC <- PC + 1",
+            Self::FETCH3 => "IR <- DR, AR <- PC",
+            Self::LDAC1 | Self::STAC1 | Self::IMM1 if textbook => {
+                "DR <- M, PC <- PC + 1, AR <- AR + 1"
+            }
+            Self::LDAC1 | Self::STAC1 | Self::IMM1 => "DR <- M, PC <- PC + 1",
+            Self::LDAC2 | Self::STAC2 | Self::IMM2 => "TR <- DR, DR <- M, PC <- PC + 1",
+            Self::LDAC3 | Self::STAC3 if textbook => "AR <- DR,TR",
+            Self::LDAC3 | Self::STAC3 => "AR <- DR",
+            Self::LDAC4 => "DR <- M",
+            Self::LDAC5 => "ACC <- DR",
+            Self::STAC4 => "DR <- ACC",
+            Self::STAC5 => "M <- DR",
+            Self::MVAC1 => "R <- ACC",
+            Self::MOVR1 => "ACC <- R",
+            Self::JMP1 | Self::JMPZY1 if textbook => "DR <- M, AR <- AR + 1",
+            Self::JMP1 | Self::JMPZY1 => "DR <- M",
+            Self::JMP2 | Self::JMPZY2 => "TR <- DR, DR <- M",
+            Self::JMP3 | Self::JMPZY3 if textbook => "PC <- DR,TR",
+            Self::JMP3 | Self::JMPZY3 => "PC <- DR",
+            Self::JMPZN1 | Self::JMPZN2 => "PC <- PC + 1",
+            Self::OUT1 => "OUTR <- ACC",
+            Self::SUB1 => "ACC <- ACC - R",
+            Self::ADD1 => "ACC <- ACC + R",
+            Self::INC1 => "ACC <- ACC + 1",
+            Self::CLAC1 => "ACC <- 0",
+            Self::AND1 => "ACC <- ACC & R",
+            Self::OR1 => "ACC <- ACC | R",
+            Self::ASHR1 => "ACC <- ACC >> 1",
+            Self::NOT1 => "ACC <- !ACC",
+            Self::HALT1 => "S <- 1",
+            Self::INT1 => "EPC <- PC, PC <- vector, IE <- 0",
+            Self::EI1 => "IE <- 1",
+            Self::DI1 => "IE <- 0",
+            Self::RETI1 => "PC <- EPC, IE <- 1",
+            Self::EXEC1 => "custom",
+            Self::IMM3 if textbook => "custom(DR,TR)",
+            Self::IMM3 => "custom(DR)",
         }
     }
 }
@@ -0,0 +1,3 @@
+//! An emulator and debugger for the Relatively Simple CPU, shared by the interface, the tests and benchmarks.
+pub mod debugger;
+pub mod emulator;
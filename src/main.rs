@@ -1,7 +1,7 @@
-mod debugger;
-mod emulator;
 mod ui;
 
+use rsc::{debugger, emulator};
+
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
@@ -39,7 +39,7 @@ impl eframe::App for Interface {
                 ui,
                 &mut self.debugger,
                 &mut self.assembler,
-                &self.editor.code,
+                &mut self.editor.code,
             );
         });
 
@@ -63,17 +63,17 @@ impl eframe::App for Interface {
                     .split_top_bottom_at_fraction(0.5);
 
                 ui.allocate_new_ui(egui::UiBuilder::new().max_rect(top), |ui| {
-                    self.cpu_state.show(ui, &self.debugger);
+                    self.cpu_state.show(ui, &mut self.debugger);
                 });
 
                 ui.allocate_new_ui(egui::UiBuilder::new().max_rect(bottom), |ui| {
                     self.variable_viewer
-                        .show(ui, &self.debugger, &self.assembler);
+                        .show(ui, &mut self.debugger, &self.assembler);
                 });
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.editor.show(ui, &mut self.debugger);
+            self.editor.show(ui, &mut self.debugger, &self.assembler);
         });
     }
 }
@@ -8,12 +8,41 @@ impl BytecodeViewer {
         "BytecodeViewer"
     }
 
+    /// Lists the assembled program disassembled with the emulator's instruction set, marking the current instruction.
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
-        _debugger: &Option<Debugger>,
-        _assembler: &Option<Assembler>,
+        debugger: &Option<Debugger>,
+        assembler: &Option<Assembler>,
     ) {
-        ui.label(self.name());
+        let (Some(debugger), Some(assembler)) = (debugger, assembler) else {
+            ui.label(self.name());
+            return;
+        };
+
+        let emulator = &debugger.emulator;
+        let current = emulator.instruction_address();
+        let mut idx = 0;
+
+        egui::ScrollArea::vertical()
+            .id_salt(self.name())
+            .show(ui, |ui| {
+                while idx < assembler.instructions.len() {
+                    let (text, len) = emulator.isa().disassemble(
+                        &assembler.instructions[idx..],
+                        assembler.word_kinds[idx],
+                        emulator.width(),
+                    );
+                    let address = assembler.origin + idx as u32;
+                    let line = egui::RichText::new(format!("{address:#06x}  {text}")).monospace();
+
+                    if address == current {
+                        ui.label(line.strong());
+                    } else {
+                        ui.label(line);
+                    }
+                    idx += len.max(1);
+                }
+            });
     }
 }
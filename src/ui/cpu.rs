@@ -1,23 +1,113 @@
-use crate::{debugger::Debugger, emulator::util::Register};
+use crate::{
+    debugger::{provenance::Location, Debugger},
+    emulator::util::Register,
+};
 
 #[derive(Default)]
 pub struct CpuState {}
 
 impl CpuState {
-    fn name(&self) -> &'static str {
-        "CpuState"
-    }
-
-    pub fn show(&mut self, ui: &mut egui::Ui, debugger: &Option<Debugger>) {
+    pub fn show(&mut self, ui: &mut egui::Ui, debugger: &mut Option<Debugger>) {
         for register in Register::iter() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} : {}",
+                    register.as_str(),
+                    debugger
+                        .as_ref()
+                        .map(|debugger| debugger.emulator.registers.get(*register))
+                        .unwrap_or(0)
+                ));
+
+                if let Some(debugger) = debugger {
+                    let location = Location::Register(*register);
+                    if let Some(write) = debugger.last_write(location) {
+                        if ui
+                            .small_button("⏮")
+                            .on_hover_text(format!(
+                                "Last written at step {} by {} @ {:#x}",
+                                write.time_step,
+                                write.instruction.map_or("?", |i| i.mnemonic),
+                                write.pc
+                            ))
+                            .clicked()
+                        {
+                            debugger.jump_to_last_write(location);
+                        }
+                    }
+                }
+            });
+        }
+
+        if let Some(debugger) = debugger {
+            let width = debugger.emulator.width();
+            match (
+                debugger.emulator.micro_pc(),
+                debugger.emulator.micro_instruction(),
+            ) {
+                (Some(micro_pc), Some(instruction)) => ui.label(format!(
+                    "µPC : {micro_pc} {} ({})",
+                    instruction.name,
+                    instruction.rtl(width)
+                )),
+                (Some(micro_pc), None) => ui.label(format!("µPC : {micro_pc}")),
+                (None, _) => {
+                    let state = debugger.emulator.state();
+                    ui.label(format!("State : {} ({})", state.as_str(), state.rtl(width)))
+                }
+            };
+            let datapath = debugger.emulator.datapath();
+            ui.label(format!(
+                "Signals : {}",
+                datapath
+                    .signals()
+                    .map(|signal| signal.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            ));
             ui.label(format!(
-                "{} : {}",
-                register.as_str(),
+                "Bus : {}  ALU : {}",
+                datapath
+                    .bus
+                    .map_or("-".to_string(), |bus| format!("{bus:#x}")),
+                datapath
+                    .alu
+                    .map_or("-".to_string(), |alu| format!("{alu:#x}"))
+            ));
+            ui.label(format!("Cycles : {}", debugger.emulator.cycles()));
+            ui.label(format!(
+                "Output : {}",
                 debugger
-                    .as_ref()
-                    .and_then(|debugger| { Some(debugger.emulator.registers.get(*register)) })
-                    .unwrap_or(0)
+                    .emulator
+                    .output()
+                    .iter()
+                    .map(|output| output.value.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
             ));
         }
+
+        if let Some(debugger) = debugger {
+            for (base, device) in debugger.emulator.memory.devices() {
+                ui.label(format!(
+                    "{} @ {base:#x} : {}",
+                    device.name(),
+                    device.describe()
+                ));
+            }
+        }
+
+        if let Some(debugger) = debugger {
+            for warning in debugger.emulator.warnings() {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Step {} : {}", warning.time_step, warning.fault),
+                );
+            }
+        }
+
+        if let Some(stop) = debugger.as_ref().and_then(|debugger| debugger.stop()) {
+            ui.colored_label(ui.visuals().error_fg_color, stop.to_string());
+        }
     }
 }
@@ -1,4 +1,4 @@
-use crate::debugger::Debugger;
+use crate::{debugger::Debugger, emulator::Assembler};
 
 const DEFAULT_ROWS: usize = 100;
 const FONT_SIZE: f32 = 12.0;
@@ -15,12 +15,20 @@ impl Editor {
     fn name(&self) -> &'static str {
         "Editor"
     }
-    pub fn show(&mut self, ui: &mut egui::Ui, _debugger: &mut Option<Debugger>) {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        debugger: &mut Option<Debugger>,
+        assembler: &Option<Assembler>,
+    ) {
         // TODO: Draw an arrow for where the program counter is and breakpoint functionality
 
         egui::ScrollArea::vertical().show(ui, |ui| {
             ui.horizontal_top(|h| {
                 self.numbering(h, &self.code);
+                if let (Some(debugger), Some(assembler)) = (debugger.as_ref(), assembler) {
+                    self.profile(h, &self.code, debugger, assembler);
+                }
 
                 let available_width = h.available_width();
                 let output = egui::TextEdit::multiline(&mut self.code)
@@ -82,4 +90,33 @@ impl Editor {
                 .layouter(&mut layouter),
         );
     }
+
+    /// Shows how often each line has been executed or accessed, lines that never were are marked.
+    fn profile(&self, ui: &mut egui::Ui, code: &str, debugger: &Debugger, assembler: &Assembler) {
+        let lines = debugger.emulator.profile().lines(assembler);
+        let untouched = ui.visuals().error_fg_color;
+        let mut layout_job = egui::text::LayoutJob::default();
+
+        for ln in 0..code.lines().count() {
+            let (label, color) = match lines.get(&ln) {
+                Some(counts) if counts.untouched() => ("never".to_string(), untouched),
+                Some(counts) if counts.executions > 0 => {
+                    (format!("x{}", counts.executions), egui::Color32::GRAY)
+                }
+                Some(counts) => (
+                    format!("r{} w{}", counts.reads, counts.writes),
+                    egui::Color32::GRAY,
+                ),
+                None => (String::new(), egui::Color32::GRAY),
+            };
+
+            layout_job.append(
+                &format!("{label}\n"),
+                0.0,
+                egui::TextFormat::simple(egui::FontId::monospace(FONT_SIZE), color),
+            );
+        }
+
+        ui.add(egui::Label::new(layout_job).selectable(false));
+    }
 }
@@ -1,22 +1,51 @@
-use crate::{debugger::Debugger, emulator::Assembler};
+use crate::{
+    debugger::{message::Message, state::State, Debugger},
+    emulator::{
+        config::{Config, Guard},
+        Assembler,
+    },
+};
+use std::{cell::RefCell, rc::Rc};
 
 const FONT_SIZE: f32 = 17.0;
+const SNAPSHOT_FILE_NAME: &str = "session.json";
 
 #[derive(Default)]
-pub struct Top {}
+pub struct Top {
+    // Contents of a snapshot picked by the user, filled in asynchronously on the web.
+    picked_snapshot: Rc<RefCell<Option<String>>>,
+    // Whether programs are assembled for a microprogrammed control unit rather than the hardwired one.
+    microprogrammed: bool,
+    // Whether programs run with the carry, overflow and negative flags and the jumps on them.
+    extended_flags: bool,
+    // Time of the last tick sent to a running debugger, in seconds.
+    last_tick: f64,
+}
 
 impl Top {
-    fn name(&self) -> &'static str {
-        "Top"
-    }
-
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
         debugger: &mut Option<Debugger>,
         assembler: &mut Option<Assembler>,
-        code: &str,
+        code: &mut String,
     ) {
+        if let Some(data) = self.picked_snapshot.borrow_mut().take() {
+            match Debugger::load(&data) {
+                Ok((loaded, source)) => {
+                    assembler.replace(Assembler::parse_with(
+                        source.clone(),
+                        loaded.emulator.memory.base(),
+                        loaded.emulator.width(),
+                        loaded.emulator.isa(),
+                    ));
+                    debugger.replace(loaded);
+                    *code = source;
+                }
+                Err(e) => log::error!("{e}"),
+            }
+        }
+
         ui.add_space(1.0);
         ui.horizontal(|ui| {
             if ui
@@ -27,20 +56,82 @@ impl Top {
                 let new_assembler = Assembler::parse(code.to_string());
                 // TODO: Spawn the debugger on another thread.
                 if new_assembler.errors.is_none() {
-                    debugger.replace(Debugger::new(&new_assembler.instructions));
+                    // Stop on programs that run into their variables or overwrite their own code, and warn
+                    // about reads of memory that was never written.
+                    let config = Config {
+                        code_guard: Guard::Stop,
+                        uninitialized_guard: Guard::Warn,
+                        extended_flags: self.extended_flags,
+                        microprogrammed: self.microprogrammed,
+                        ..Default::default()
+                    };
+                    match Debugger::with_config(&new_assembler.instructions, config) {
+                        Ok(mut new_debugger) => {
+                            new_debugger
+                                .emulator
+                                .classify(new_assembler.word_kinds.clone());
+                            debugger.replace(new_debugger);
+                        }
+                        Err(e) => log::error!("{e}"),
+                    }
                 }
                 assembler.replace(new_assembler);
             };
 
+            ui.checkbox(&mut self.microprogrammed, "µ")
+                .on_hover_text("Microprogrammed control unit, taking effect when next assembled");
+            ui.checkbox(&mut self.extended_flags, "CVN").on_hover_text(
+                "Carry, overflow and negative flags, taking effect when next assembled",
+            );
+
+            if ui
+                .button(egui::RichText::new("📂").font(egui::FontId::monospace(FONT_SIZE)))
+                .on_hover_text("Load Snapshot")
+                .clicked()
+            {
+                self.pick_snapshot();
+            }
+
+            if let Some(debugger) = debugger {
+                if ui
+                    .button(egui::RichText::new("💾").font(egui::FontId::monospace(FONT_SIZE)))
+                    .on_hover_text("Save Snapshot")
+                    .clicked()
+                {
+                    match debugger.save(code) {
+                        Ok(data) => save_snapshot(data),
+                        Err(e) => log::error!("{e}"),
+                    }
+                }
+            }
+
             if let Some(debugger) = debugger {
+                let state = tick(ui, debugger, &mut self.last_tick);
+                let paused = matches!(state, State::Paused);
+                let at_start = debugger.emulator.time_step() == debugger.emulator.first_step();
                 let (
                     pause_enabled,
                     run_enabled,
+                    resume_enabled,
                     step_forward_enabled,
                     step_backward_enabled,
                     step_over_enabled,
+                    micro_step_forward_enabled,
+                    micro_step_backward_enabled,
+                    reverse_continue_enabled,
                     restart_enabled,
-                ) = (false, false, false, false, false, false);
+                ) = (
+                    matches!(state, State::Running),
+                    paused,
+                    debugger.emulator.halted(),
+                    paused,
+                    !matches!(state, State::Running) && !at_start,
+                    paused,
+                    paused,
+                    !matches!(state, State::Running) && !at_start,
+                    !matches!(state, State::Running) && !at_start,
+                    !at_start,
+                );
 
                 ui.add_enabled_ui(pause_enabled, |ui| {
                     if ui
@@ -50,7 +141,9 @@ impl Top {
                         .on_hover_text("Pause")
                         .on_disabled_hover_text("Pause")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::Pause);
+                    };
                 });
 
                 ui.add_enabled_ui(run_enabled, |ui| {
@@ -61,7 +154,35 @@ impl Top {
                         .on_hover_text("Run")
                         .on_disabled_hover_text("Run")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::Run);
+                    };
+                });
+
+                ui.add_enabled_ui(resume_enabled, |ui| {
+                    if ui
+                        .button(
+                            egui::RichText::new("⏯").font(egui::FontId::proportional(FONT_SIZE)),
+                        )
+                        .on_hover_text("Resume after HALT")
+                        .on_disabled_hover_text("Resume after HALT")
+                        .clicked()
+                    {
+                        debugger.update(Message::Resume);
+                    };
+                });
+
+                ui.add_enabled_ui(reverse_continue_enabled, |ui| {
+                    if ui
+                        .button(
+                            egui::RichText::new("⏪").font(egui::FontId::proportional(FONT_SIZE)),
+                        )
+                        .on_hover_text("Reverse Continue")
+                        .on_disabled_hover_text("Reverse Continue")
+                        .clicked()
+                    {
+                        debugger.update(Message::ReverseContinue);
+                    };
                 });
 
                 ui.add_enabled_ui(step_backward_enabled, |ui| {
@@ -72,7 +193,9 @@ impl Top {
                         .on_hover_text("Step Backward")
                         .on_disabled_hover_text("Step Backward")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::StepBackward);
+                    };
                 });
 
                 ui.add_enabled_ui(step_forward_enabled, |ui| {
@@ -83,7 +206,9 @@ impl Top {
                         .on_hover_text("Step Forward")
                         .on_disabled_hover_text("Step Forward")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::StepForward);
+                    };
                 });
 
                 ui.add_enabled_ui(step_over_enabled, |ui| {
@@ -94,7 +219,35 @@ impl Top {
                         .on_hover_text("Step Over")
                         .on_disabled_hover_text("Step Over")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::StepOver);
+                    };
+                });
+
+                ui.add_enabled_ui(micro_step_backward_enabled, |ui| {
+                    if ui
+                        .button(
+                            egui::RichText::new("µ⬅").font(egui::FontId::proportional(FONT_SIZE)),
+                        )
+                        .on_hover_text("Micro-Step Backward")
+                        .on_disabled_hover_text("Micro-Step Backward")
+                        .clicked()
+                    {
+                        debugger.update(Message::MicroStepBackward);
+                    };
+                });
+
+                ui.add_enabled_ui(micro_step_forward_enabled, |ui| {
+                    if ui
+                        .button(
+                            egui::RichText::new("µ➡").font(egui::FontId::proportional(FONT_SIZE)),
+                        )
+                        .on_hover_text("Micro-Step Forward")
+                        .on_disabled_hover_text("Micro-Step Forward")
+                        .clicked()
+                    {
+                        debugger.update(Message::MicroStepForward);
+                    };
                 });
 
                 ui.add_enabled_ui(restart_enabled, |ui| {
@@ -105,10 +258,114 @@ impl Top {
                         .on_hover_text("Restart")
                         .on_disabled_hover_text("Restart")
                         .clicked()
-                    {};
+                    {
+                        debugger.update(Message::Restart);
+                    };
                 });
+
+                let mut time_step = debugger.emulator.time_step();
+                let range = debugger.emulator.first_step()..=debugger.emulator.last_step();
+                if ui
+                    .add(egui::Slider::new(&mut time_step, range).text("Step"))
+                    .on_hover_text("Timeline")
+                    .changed()
+                {
+                    debugger.update(Message::Seek(time_step));
+                }
             }
         });
         ui.add_space(1.0);
     }
 }
+
+/// Ticks a running debugger at its instructions per second, returning the state it is left in.
+fn tick(ui: &egui::Ui, debugger: &mut Debugger, last_tick: &mut f64) -> State {
+    if let State::Running = debugger.state() {
+        let interval = 1.0 / debugger.instructions_per_second.max(1) as f64;
+        let now = ui.input(|i| i.time);
+        if now - *last_tick >= interval {
+            debugger.update(Message::Tick);
+            *last_tick = now;
+        }
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_secs_f64(interval));
+    }
+
+    debugger.state()
+}
+
+impl Top {
+    /// Asks the user for a snapshot file, its contents are picked up on the next frame.
+    fn pick_snapshot(&self) {
+        let picked = self.picked_snapshot.clone();
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Some(file) = rfd::AsyncFileDialog::new()
+                        .add_filter("RSC snapshot", &["json"])
+                        .pick_file()
+                        .await
+                    {
+                        let data = String::from_utf8_lossy(&file.read().await).into_owned();
+                        picked.borrow_mut().replace(data);
+                    }
+                });
+            } else {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("RSC snapshot", &["json"])
+                    .pick_file()
+                {
+                    match std::fs::read_to_string(path) {
+                        Ok(data) => {
+                            picked.borrow_mut().replace(data);
+                        }
+                        Err(e) => log::error!("{e}"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Hands a snapshot to the user, as a file download on the web.
+fn save_snapshot(data: String) {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use eframe::wasm_bindgen::JsCast as _;
+
+            let download = || -> Result<(), wasm_bindgen::JsValue> {
+                let parts = js_sys::Array::of1(&data.into());
+                let options = web_sys::BlobPropertyBag::new();
+                options.set_type("application/json");
+                let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+                let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+                let anchor = web_sys::window()
+                    .and_then(|window| window.document())
+                    .ok_or("No document")?
+                    .create_element("a")?
+                    .dyn_into::<web_sys::HtmlAnchorElement>()?;
+                anchor.set_href(&url);
+                anchor.set_download(SNAPSHOT_FILE_NAME);
+                anchor.click();
+
+                web_sys::Url::revoke_object_url(&url)
+            };
+
+            if let Err(e) = download() {
+                log::error!("{e:?}");
+            }
+        } else {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("RSC snapshot", &["json"])
+                .set_file_name(SNAPSHOT_FILE_NAME)
+                .save_file()
+            {
+                if let Err(e) = std::fs::write(path, data) {
+                    log::error!("{e}");
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{debugger::Debugger, emulator::Assembler};
+use crate::{
+    debugger::{provenance::Location, Debugger},
+    emulator::{util::WordKind, Assembler},
+};
 
 #[derive(Default)]
 pub struct VariableViewer {}
@@ -11,9 +14,61 @@ impl VariableViewer {
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
-        _debugger: &Option<Debugger>,
-        _assembler: &Option<Assembler>,
+        debugger: &mut Option<Debugger>,
+        assembler: &Option<Assembler>,
     ) {
-        ui.label(self.name());
+        let (Some(debugger), Some(assembler)) = (debugger, assembler) else {
+            ui.label(self.name());
+            return;
+        };
+
+        // Only labels on data words are variables, the rest mark code.
+        let mut variables: Vec<(&String, u32)> = assembler
+            .symbol_map
+            .iter()
+            .filter(|(_, &address)| {
+                address
+                    .checked_sub(assembler.origin)
+                    .and_then(|idx| assembler.word_kinds.get(idx as usize))
+                    == Some(&WordKind::Data)
+            })
+            .map(|(name, &address)| (name, address))
+            .collect();
+        variables.sort_by_key(|(_, address)| *address);
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (name, address) in variables {
+                ui.horizontal(|ui| {
+                    let emulator = &debugger.emulator;
+                    let value = emulator.memory.get(address).unwrap_or(0);
+                    // Variables changed by the step just taken show what they held before it.
+                    let before = emulator
+                        .value_at(address, emulator.time_step().saturating_sub(1))
+                        .unwrap_or(value);
+                    if value == before {
+                        ui.label(format!("{name} @ {address:#x} : {value}"));
+                    } else {
+                        ui.label(format!("{name} @ {address:#x} : {value} (was {before})"));
+                    }
+
+                    let location = Location::Address(address);
+                    if let Some(write) = debugger.last_write(location) {
+                        if ui
+                            .small_button("⏮")
+                            .on_hover_text(format!(
+                                "Last written at step {} by {} @ {:#x}, from {}",
+                                write.time_step,
+                                write.instruction.map_or("?", |i| i.mnemonic),
+                                write.pc,
+                                write.previous
+                            ))
+                            .clicked()
+                        {
+                            debugger.jump_to_last_write(location);
+                        }
+                    }
+                });
+            }
+        });
     }
 }
@@ -0,0 +1,33 @@
+//! Helpers shared by the tests, each test only uses some of them.
+#![allow(dead_code)]
+
+use rsc::emulator::{config::Config, util::Fault, Assembler, Emulator};
+
+/// The number of words in the address space of most machines tested, enough for the small programs they run.
+pub const MEMORY_SIZE: usize = 16;
+
+/// A machine with a small address space.
+pub fn config() -> Config {
+    Config {
+        memory_size: MEMORY_SIZE,
+        ..Default::default()
+    }
+}
+
+/// A machine running a program assembled from 'source' at its base and width, with its words classified.
+pub fn assemble(source: &str, config: Config) -> Emulator {
+    let assembler = Assembler::parse_for(source.to_string(), config.base, config.width);
+    assert!(assembler.errors.is_none(), "{:?}", assembler.errors);
+
+    let mut emulator = Emulator::with_config(&assembler.instructions, config).unwrap();
+    emulator.classify(assembler.word_kinds);
+    emulator
+}
+
+/// Runs until the machine halts, or faults.
+pub fn run(emulator: &mut Emulator) -> Result<(), Fault> {
+    while !emulator.halted() {
+        emulator.cycle()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Instructions added to the instruction set with custom operations.
+use rsc::emulator::{
+    config::{Config, Reset},
+    isa::{Context, Instruction, Isa, Operation},
+    util::{Fault, Register},
+    Assembler, Emulator,
+};
+
+// The stack grows down from the end of memory, with R as its pointer.
+const MEMORY_SIZE: u32 = 32;
+
+/// Pushes the return address and jumps to the operand.
+fn call(context: &mut Context, operand: u32) -> Result<(), Fault> {
+    let sp = context.get(Register::R) - 1;
+    context.write(sp, context.get(Register::PC))?;
+    context.set(Register::R, sp);
+    context.set(Register::PC, operand);
+    Ok(())
+}
+
+/// Pops the return address into PC.
+fn ret(context: &mut Context) -> Result<(), Fault> {
+    let sp = context.get(Register::R);
+    let pc = context.read(sp)?;
+    context.set(Register::R, sp + 1);
+    context.set(Register::PC, pc);
+    Ok(())
+}
+
+fn isa() -> Isa {
+    Isa::rsc()
+        .with(Instruction::new(
+            "CALL",
+            0x20,
+            Operation::CustomWithOperand(call),
+        ))
+        .with(Instruction::new("RET", 0x21, Operation::Custom(ret)))
+}
+
+const SOURCE: &str = "
+    CALL TWICE
+    CALL TWICE
+    OUT
+    HALT
+TWICE:
+    INC
+    INC
+    RET
+";
+
+fn emulator(stack: u32, microprogrammed: bool) -> Emulator {
+    let isa = isa();
+    let assembler = Assembler::parse_with(SOURCE.to_string(), 0, Default::default(), &isa);
+    let mut emulator = Emulator::with_config(
+        &assembler.instructions,
+        Config {
+            memory_size: MEMORY_SIZE as usize,
+            microprogrammed,
+            reset: Reset::default().with_register(Register::R, stack),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    emulator.set_isa(isa).unwrap();
+    emulator
+}
+
+#[test]
+fn call_and_return_through_memory() {
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(MEMORY_SIZE, microprogrammed);
+        while !emulator.halted() {
+            emulator.cycle().unwrap();
+        }
+
+        let output: Vec<u32> = emulator.output().iter().map(|o| o.value).collect();
+        assert_eq!(output, vec![4], "microprogrammed: {microprogrammed}");
+        assert_eq!(emulator.registers.get(Register::R), MEMORY_SIZE);
+        assert_eq!(emulator.memory.get(MEMORY_SIZE - 1), Some(4));
+    }
+}
+
+#[test]
+fn custom_operations_are_undone_on_a_fault() {
+    for microprogrammed in [false, true] {
+        // The stack is past the end of memory, so that the push faults.
+        let mut emulator = emulator(2 * MEMORY_SIZE, microprogrammed);
+
+        let registers = emulator.registers.values();
+        assert_eq!(emulator.cycle(), Err(Fault::AddressOutOfRange(0, 63)));
+        assert_eq!(emulator.registers.values(), registers);
+    }
+}
+
+#[test]
+fn custom_operations_step_back() {
+    let mut emulator = emulator(MEMORY_SIZE, false);
+    emulator.cycle().unwrap();
+    assert_eq!(emulator.registers.get(Register::PC), 6);
+    assert_eq!(emulator.memory.get(MEMORY_SIZE - 1), Some(2));
+
+    while emulator.step_backward() {}
+    assert_eq!(emulator.registers.get(Register::PC), 0);
+    assert_eq!(emulator.registers.get(Register::R), MEMORY_SIZE);
+    assert_eq!(emulator.memory.get(MEMORY_SIZE - 1), Some(0));
+}
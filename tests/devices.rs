@@ -0,0 +1,174 @@
+//! The devices mapped into the address space, and their state through time.
+mod common;
+
+use rsc::{
+    debugger::Debugger,
+    emulator::{
+        config::{Config, History},
+        device::{Device, InputQueue, LedBank, Terminal, Timer},
+        util::Register,
+        Emulator,
+    },
+};
+
+const TERMINAL: u32 = 14;
+const TIMER: u32 = 15;
+
+// LDAC the character at 7, STAC it to the terminal twice and HALT.
+const PROGRAM: [u32; 8] = [1, 7, 2, TERMINAL, 2, TERMINAL, 0, 0x41];
+
+#[test]
+fn input_queues_give_their_values_in_order() {
+    let mut queue = InputQueue::new(&[5, 9]);
+    assert_eq!(queue.peek(1), 2);
+    assert!(queue.interrupt());
+
+    assert_eq!(queue.peek(0), 5);
+    assert_eq!(queue.read(0), 5);
+    assert_eq!(queue.read(1), 1);
+    assert_eq!(queue.read(0), 9);
+
+    // Once empty, reads give zero and no interrupt is requested.
+    assert_eq!(queue.read(1), 0);
+    assert_eq!(queue.read(0), 0);
+    assert!(!queue.interrupt());
+}
+
+#[test]
+fn terminals_print_the_low_byte_of_each_write() {
+    let mut terminal = Terminal::new();
+    terminal.write(0, 'H' as u32);
+    terminal.write(0, 0x169);
+    assert_eq!(terminal.text(), "Hi");
+    assert_eq!(terminal.read(0), 0);
+    assert_eq!(terminal.describe(), "\"Hi\"");
+}
+
+#[test]
+fn timers_count_every_micro_operation() {
+    let mut emulator = Emulator::with_config(&[10, 10, 0], common::config()).unwrap();
+    emulator.memory.attach(TIMER, Box::new(Timer::new()));
+    common::run(&mut emulator).unwrap();
+    assert_eq!(
+        emulator.memory.get(TIMER),
+        Some(emulator.time_step() as u32)
+    );
+
+    let mut timer = Timer::new();
+    timer.write(0, 41);
+    timer.tick();
+    assert_eq!(timer.read(0), 42);
+}
+
+#[test]
+fn led_banks_hold_the_segments_of_each_digit() {
+    let mut leds = LedBank::new(3);
+    assert_eq!(leds.size(), 3);
+    leds.write(0, 0b0111_1111);
+    leds.write(2, 0b0000_0110);
+    assert_eq!(leds.segments(), &[0b0111_1111, 0, 0b0000_0110]);
+    assert_eq!(leds.read(2), 0b0000_0110);
+    assert_eq!(leds.describe(), "01111111 00000000 00000110");
+}
+
+#[test]
+fn devices_are_restored_by_the_address_they_are_attached_at() {
+    let config = Config {
+        history: History {
+            checkpoint_interval: 1,
+            ..Default::default()
+        },
+        ..common::config()
+    };
+    let mut debugger = Debugger::with_config(&PROGRAM, config).unwrap();
+    debugger
+        .emulator
+        .memory
+        .attach(TERMINAL, Box::new(Terminal::new()));
+    debugger
+        .emulator
+        .memory
+        .attach(TIMER, Box::new(Timer::new()));
+    debugger.stepi(4);
+    assert!(debugger.emulator.halted());
+    assert_eq!(debugger.emulator.registers.get(Register::ACC), 0x41);
+
+    // Attached again in the other order after restoring, the state of each is still found in the checkpoints.
+    let (mut restored, _) = Debugger::load(&debugger.save("").unwrap()).unwrap();
+    restored
+        .emulator
+        .memory
+        .attach(TIMER, Box::new(Timer::new()));
+    restored
+        .emulator
+        .memory
+        .attach(TERMINAL, Box::new(Terminal::new()));
+
+    let describe = |debugger: &Debugger| {
+        let mut devices: Vec<(u32, String)> = debugger
+            .emulator
+            .memory
+            .devices()
+            .map(|(base, device)| (base, device.describe()))
+            .collect();
+        devices.sort();
+        devices
+    };
+    for time_step in (1..debugger.emulator.last_step()).rev() {
+        debugger.seek(time_step);
+        restored.seek(time_step);
+        assert_eq!(describe(&restored), describe(&debugger), "at {time_step}");
+    }
+    assert_eq!(describe(&restored)[0].1, "\"\"");
+}
+
+/// What the terminal attached to the machine describes, its text in quotes.
+fn terminal(emulator: &Emulator) -> String {
+    emulator
+        .memory
+        .devices()
+        .find(|(base, _)| *base == TERMINAL)
+        .map(|(_, device)| device.describe())
+        .unwrap()
+}
+
+#[test]
+fn terminal_output_follows_the_timeline() {
+    for microprogrammed in [false, true] {
+        let mut emulator = Emulator::with_config(
+            &PROGRAM,
+            Config {
+                microprogrammed,
+                ..common::config()
+            },
+        )
+        .unwrap();
+        emulator.memory.attach(TERMINAL, Box::new(Terminal::new()));
+
+        emulator.cycle().unwrap();
+        emulator.cycle().unwrap();
+        let first_write = emulator.time_step();
+        common::run(&mut emulator).unwrap();
+        let halted = emulator.time_step();
+        assert_eq!(terminal(&emulator), "\"AA\"");
+
+        // Stepping back over the second STAC takes its character back off the terminal.
+        while emulator.time_step() > first_write {
+            assert!(emulator.step_backward());
+        }
+        assert_eq!(terminal(&emulator), "\"A\"");
+        assert!(emulator.step_backward());
+        assert_eq!(terminal(&emulator), "\"\"");
+
+        emulator.seek(halted);
+        assert_eq!(terminal(&emulator), "\"AA\"");
+        emulator.seek(0);
+        assert_eq!(terminal(&emulator), "\"\"");
+        emulator.seek(first_write);
+        assert_eq!(terminal(&emulator), "\"A\"");
+
+        // Running again from there writes the second character once more.
+        common::run(&mut emulator).unwrap();
+        assert_eq!(terminal(&emulator), "\"AA\"");
+    }
+}
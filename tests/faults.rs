@@ -0,0 +1,156 @@
+//! Every fault raised during execution, undone by the emulator and reported by the debugger.
+mod common;
+
+use rsc::{
+    debugger::{progress::Stop, Debugger},
+    emulator::{
+        config::{Config, Guard},
+        microcode::Next,
+        util::{Fault, Register, WordKind},
+        Emulator,
+    },
+};
+
+// Opcodes of the instructions used, the machine is wide so operands take a single word.
+const HALT: u32 = 0;
+const LDAC: u32 = 1;
+const STAC: u32 = 2;
+const JMP: u32 = 5;
+const INC: u32 = 10;
+
+/// A program faulting once it has run for a while, along with the machine it runs on.
+struct Case {
+    program: &'static [u32],
+    config: Config,
+    // Prepares the machine before it runs.
+    setup: fn(&mut Emulator),
+    fault: Fault,
+}
+
+fn cases(microprogrammed: bool) -> Vec<Case> {
+    let config = Config {
+        microprogrammed,
+        ..common::config()
+    };
+
+    vec![
+        Case {
+            program: &[INC, 0x3F],
+            config,
+            setup: |_| (),
+            fault: Fault::IllegalOpcode(1, 0x3F),
+        },
+        Case {
+            program: &[INC, LDAC, 20, HALT],
+            config,
+            setup: |_| (),
+            fault: Fault::AddressOutOfRange(1, 20),
+        },
+        Case {
+            program: &[JMP, 9],
+            config,
+            setup: |_| (),
+            fault: Fault::FetchOutOfProgram(9),
+        },
+        Case {
+            program: &[JMP, 2, 5],
+            config: Config {
+                code_guard: Guard::Stop,
+                ..config
+            },
+            setup: |emulator| {
+                emulator.classify(vec![WordKind::Code, WordKind::Code, WordKind::Data])
+            },
+            fault: Fault::ExecuteData(2),
+        },
+        Case {
+            program: &[INC, STAC, 0, HALT],
+            config: Config {
+                code_guard: Guard::Stop,
+                ..config
+            },
+            setup: |_| (),
+            fault: Fault::SelfModifyingCode(1, 0),
+        },
+        Case {
+            program: &[INC, LDAC, 12, HALT],
+            config: Config {
+                uninitialized_guard: Guard::Stop,
+                ..config
+            },
+            setup: |_| (),
+            fault: Fault::UninitializedRead(1, 12),
+        },
+    ]
+}
+
+/// INC twice and HALT, with the micro-routine of INC continuing past the end of the microcode ROM.
+fn micro_address_out_of_range() -> Case {
+    Case {
+        program: &[INC, INC, HALT],
+        config: Config {
+            microprogrammed: true,
+            ..common::config()
+        },
+        setup: |emulator| {
+            let mut microcode = emulator.microcode().unwrap().clone();
+            let inc = microcode
+                .rom
+                .iter()
+                .position(|instruction| instruction.name == "INC1")
+                .unwrap();
+            microcode.rom[inc].next = Next::Goto(9999);
+            assert!(emulator.set_microcode(Some(microcode)));
+        },
+        fault: Fault::MicroAddressOutOfRange(0, 9999),
+    }
+}
+
+fn all_cases() -> Vec<Case> {
+    let mut cases: Vec<Case> = [false, true].into_iter().flat_map(cases).collect();
+    cases.push(micro_address_out_of_range());
+    cases
+}
+
+#[test]
+fn faulting_instructions_are_undone() {
+    for case in all_cases() {
+        let mut emulator = Emulator::with_config(case.program, case.config).unwrap();
+        (case.setup)(&mut emulator);
+
+        let mut boundary = emulator.time_step();
+        let fault = loop {
+            match emulator.cycle() {
+                Ok(()) => boundary = emulator.time_step(),
+                Err(fault) => break fault,
+            }
+            assert!(!emulator.halted(), "{:?} was not raised", case.fault);
+        };
+
+        assert_eq!(fault, case.fault);
+        assert_eq!(emulator.time_step(), boundary, "{fault}");
+        assert_eq!(emulator.last_step(), boundary, "{fault}");
+        assert!(emulator.at_instruction_boundary(), "{fault}");
+    }
+}
+
+#[test]
+fn debuggers_stop_on_faults() {
+    for case in all_cases() {
+        let mut debugger = Debugger::with_config(case.program, case.config).unwrap();
+        (case.setup)(&mut debugger.emulator);
+
+        debugger.stepi(10);
+        assert_eq!(debugger.stop(), Some(Stop::Fault(case.fault)));
+        assert_eq!(debugger.fault(), Some(case.fault));
+
+        // Nothing more is executed while stopped.
+        let (time_step, pc) = (
+            debugger.emulator.time_step(),
+            debugger.emulator.registers.get(Register::PC),
+        );
+        debugger.stepi(1);
+        assert_eq!(debugger.emulator.time_step(), time_step);
+        assert_eq!(debugger.emulator.registers.get(Register::PC), pc);
+    }
+}
@@ -0,0 +1,115 @@
+//! The extended C, V and N flags, and the jumps on them, at the edges of both word widths.
+mod common;
+
+use rsc::emulator::{
+    config::{Config, WordWidth},
+    util::Register,
+    Assembler,
+};
+
+/// An operation on ACC and R, and the ACC, C, V and N it leaves.
+struct Case {
+    mnemonic: &'static str,
+    acc: u32,
+    r: u32,
+    result: u32,
+    flags: [u32; 3],
+}
+
+/// The edges of a word of the given width: its largest value, as unsigned and signed, and its sign bit.
+fn cases(width: WordWidth) -> Vec<Case> {
+    let (max, sign) = (width.data_mask(), width.sign_bit());
+    let case = |mnemonic, acc, r, result, flags| Case {
+        mnemonic,
+        acc,
+        r,
+        result,
+        flags,
+    };
+
+    vec![
+        case("ADD", 1, 2, 3, [0, 0, 0]),
+        case("ADD", max, 1, 0, [1, 0, 0]),
+        case("ADD", sign - 1, 1, sign, [0, 1, 1]),
+        case("ADD", sign, sign, 0, [1, 1, 0]),
+        case("ADD", max, max, max - 1, [1, 0, 1]),
+        case("SUB", 5, 5, 0, [0, 0, 0]),
+        case("SUB", 0, 1, max, [1, 0, 1]),
+        case("SUB", sign, 1, sign - 1, [0, 1, 0]),
+        case("SUB", sign - 1, max, sign, [1, 1, 1]),
+        case("INC", 1, 0, 2, [0, 0, 0]),
+        case("INC", max, 0, 0, [1, 0, 0]),
+        case("INC", sign - 1, 0, sign, [0, 1, 1]),
+        case("ASHR", 2, 0, 1, [0, 0, 0]),
+        case("ASHR", sign | 1, 0, sign >> 1, [1, 0, 0]),
+    ]
+}
+
+/// Loads R and ACC, performs the operation and then 'jump', which lands on TAKEN when its flag is set.
+fn source(case: &Case, jump: &str) -> String {
+    format!(
+        "LDAC R\nMVAC\nLDAC ACC\n{}\n{jump} TAKEN\nHALT\nTAKEN:\nHALT\nR: {:X}\nACC: {:X}",
+        case.mnemonic, case.r, case.acc
+    )
+}
+
+#[test]
+fn operations_set_the_flags_and_jumps_follow_them() {
+    for width in [WordWidth::Textbook, WordWidth::Wide] {
+        for case in cases(width) {
+            for (jump, flag) in [("JMPC", 0), ("JMPV", 1), ("JMPN", 2)] {
+                for microprogrammed in [false, true] {
+                    let source = source(&case, jump);
+                    let config = Config {
+                        width,
+                        extended_flags: true,
+                        microprogrammed,
+                        ..common::config()
+                    };
+                    let mut emulator = common::assemble(&source, config);
+                    let taken = Assembler::parse_for(source.clone(), 0, width).symbol_map["TAKEN"];
+                    let context = format!(
+                        "{} of {:#x} and {:#x} then {jump} in {}",
+                        case.mnemonic,
+                        case.acc,
+                        case.r,
+                        width.as_str()
+                    );
+
+                    common::run(&mut emulator).unwrap();
+                    assert_eq!(
+                        emulator.registers.get(Register::ACC),
+                        case.result,
+                        "{context}"
+                    );
+                    let flags = [Register::C, Register::V, Register::N]
+                        .map(|reg| emulator.registers.get(reg));
+                    assert_eq!(flags, case.flags, "{context}");
+                    assert_eq!(
+                        emulator.profile().get(taken).executions,
+                        case.flags[flag] as u64,
+                        "{context}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn flags_are_left_alone_without_the_extension() {
+    let case = Case {
+        mnemonic: "ADD",
+        acc: u32::MAX,
+        r: 1,
+        result: 0,
+        flags: [0, 0, 0],
+    };
+    let source = source(&case, "JMPZ");
+    let mut emulator = common::assemble(&source, common::config());
+    common::run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), case.result);
+    for reg in [Register::C, Register::V, Register::N] {
+        assert_eq!(emulator.registers.get(reg), 0, "{reg:?}");
+    }
+}
@@ -0,0 +1,138 @@
+//! Guards catching likely bugs in the running program.
+mod common;
+
+use common::run;
+use rsc::emulator::{
+    config::{Config, Guard},
+    util::Fault,
+    Emulator,
+};
+
+// Opcodes of the instructions used, the machine is wide so operands take a single word.
+const HALT: u32 = 0;
+const LDAC: u32 = 1;
+const STAC: u32 = 2;
+
+fn emulator(program: &[u32], code_guard: Guard) -> Emulator {
+    Emulator::with_config(
+        program,
+        Config {
+            code_guard,
+            ..common::config()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn stores_over_executed_opcodes_are_caught() {
+    let mut emulator = emulator(&[STAC, 0, HALT], Guard::Stop);
+    assert_eq!(run(&mut emulator), Err(Fault::SelfModifyingCode(0, 0)));
+}
+
+#[test]
+fn stores_over_executed_operands_are_caught() {
+    for microprogrammed in [false, true] {
+        let mut emulator = Emulator::with_config(
+            &[LDAC, 5, STAC, 1, HALT, 7],
+            Config {
+                code_guard: Guard::Stop,
+                microprogrammed,
+                ..common::config()
+            },
+        )
+        .unwrap();
+        assert_eq!(run(&mut emulator), Err(Fault::SelfModifyingCode(2, 1)));
+    }
+}
+
+#[test]
+fn stores_over_code_not_yet_executed_are_allowed() {
+    let mut emulator = emulator(&[STAC, 3, HALT, LDAC, 5], Guard::Stop);
+    assert_eq!(run(&mut emulator), Ok(()));
+}
+
+#[test]
+fn warnings_let_the_store_through() {
+    let mut emulator = emulator(&[LDAC, 5, STAC, 1, HALT, 7], Guard::Warn);
+    assert_eq!(run(&mut emulator), Ok(()));
+    assert_eq!(emulator.memory.get(1), Some(7));
+
+    let faults: Vec<Fault> = emulator.warnings().iter().map(|w| w.fault).collect();
+    assert_eq!(faults, vec![Fault::SelfModifyingCode(2, 1)]);
+}
+
+#[test]
+fn executed_words_follow_the_timeline() {
+    // Either the LDAC is executed before the STAC over its operand, or the STAC is run on its own.
+    let mut emulator = emulator(&[HALT, LDAC, 9, STAC, 2, HALT], Guard::Warn);
+    run(&mut emulator).unwrap();
+    let halted_at = emulator.time_step();
+
+    assert!(emulator.resume(Some(1)));
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.warnings().len(), 1);
+
+    emulator.seek(halted_at);
+    assert!(emulator.resume(Some(3)));
+    run(&mut emulator).unwrap();
+    assert!(emulator.warnings().is_empty());
+}
+
+#[test]
+fn guards_are_off_by_default() {
+    let mut emulator = Emulator::new(&[LDAC, 5, STAC, 1, HALT, 7]).unwrap();
+    assert_eq!(run(&mut emulator), Ok(()));
+    assert!(emulator.warnings().is_empty());
+}
+
+#[test]
+fn reads_of_words_never_written_are_caught() {
+    // Reads address 9, past the program, which is only written by the second run through.
+    let program = [LDAC, 9, STAC, 9, HALT];
+    let mut emulator = Emulator::with_config(
+        &program,
+        Config {
+            uninitialized_guard: Guard::Stop,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    assert_eq!(run(&mut emulator), Err(Fault::UninitializedRead(0, 9)));
+
+    let mut emulator = Emulator::with_config(
+        &program,
+        Config {
+            uninitialized_guard: Guard::Warn,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    run(&mut emulator).unwrap();
+    assert!(emulator.resume(Some(0)));
+    run(&mut emulator).unwrap();
+
+    let faults: Vec<Fault> = emulator.warnings().iter().map(|w| w.fault).collect();
+    assert_eq!(faults, vec![Fault::UninitializedRead(0, 9)]);
+}
+
+#[test]
+fn warnings_follow_the_timeline() {
+    // The read of address 9 is warned about, then the store over the executed LDAC operand faults.
+    let mut emulator = Emulator::with_config(
+        &[LDAC, 9, STAC, 1, HALT],
+        Config {
+            code_guard: Guard::Stop,
+            uninitialized_guard: Guard::Warn,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    assert_eq!(run(&mut emulator), Err(Fault::SelfModifyingCode(2, 1)));
+    assert_eq!(emulator.warnings().len(), 1);
+
+    // Running again from the start warns about the read again, rather than adding to the warning before.
+    emulator.seek(0);
+    assert_eq!(run(&mut emulator), Err(Fault::SelfModifyingCode(2, 1)));
+    assert_eq!(emulator.warnings().len(), 1);
+}
@@ -0,0 +1,196 @@
+//! Moving backward and forward through the timeline around faults.
+mod common;
+
+use common::MEMORY_SIZE;
+use rsc::emulator::{
+    config::{Config, History},
+    util::{Fault, Register},
+    Emulator,
+};
+
+// CLAC, INC, STAC 0xff and HALT for the wide machine, the store is past the end of the address space.
+const PROGRAM: [u32; 5] = [11, 10, 2, 0xff, 0];
+
+fn emulator(record: bool, microprogrammed: bool) -> Emulator {
+    Emulator::with_config(
+        &PROGRAM,
+        Config {
+            history: History {
+                record,
+                ..Default::default()
+            },
+            microprogrammed,
+            ..common::config()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn faulting_cycle_is_undone() {
+    for (record, microprogrammed) in [(true, false), (true, true), (false, false), (false, true)] {
+        let mut emulator = emulator(record, microprogrammed);
+        emulator.cycle().unwrap();
+        emulator.cycle().unwrap();
+
+        let (registers, time_step) = (emulator.registers.values(), emulator.time_step());
+        assert_eq!(
+            emulator.cycle(),
+            Err(Fault::AddressOutOfRange(2, 0xff)),
+            "record: {record}, microprogrammed: {microprogrammed}"
+        );
+        assert_eq!(emulator.registers.values(), registers);
+        assert_eq!(emulator.time_step(), time_step);
+        assert_eq!(emulator.last_step(), time_step);
+        assert!(emulator.at_instruction_boundary());
+    }
+}
+
+#[test]
+fn faulting_micro_step_keeps_the_steps_before_it() {
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(true, microprogrammed);
+        emulator.cycle().unwrap();
+        emulator.cycle().unwrap();
+        let start = emulator.time_step();
+
+        let fault = loop {
+            if let Err(fault) = emulator.micro_step() {
+                break fault;
+            }
+        };
+        assert_eq!(fault, Fault::AddressOutOfRange(2, 0xff));
+
+        // The steps of the store before the fault can be undone and redone.
+        let (registers, time_step) = (emulator.registers.values(), emulator.time_step());
+        assert!(time_step > start);
+        assert_eq!(emulator.last_step(), time_step);
+        emulator.seek(start);
+        assert_eq!(emulator.registers.get(Register::ACC), 1);
+        assert!(emulator.at_instruction_boundary());
+        emulator.seek(time_step);
+        assert_eq!(emulator.registers.values(), registers);
+
+        // The fault happens again in the same place.
+        assert_eq!(emulator.micro_step(), Err(fault));
+        assert_eq!(emulator.registers.values(), registers);
+    }
+}
+
+#[test]
+fn stepping_back_before_a_fault_allows_another_future() {
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(true, microprogrammed);
+        emulator.cycle().unwrap();
+        emulator.cycle().unwrap();
+        assert!(emulator.cycle().is_err());
+
+        // Back before INC, then around the store by moving PC onto the HALT.
+        while emulator.step_backward() && !emulator.at_instruction_boundary() {}
+        assert_eq!(emulator.registers.get(Register::PC), 1);
+        emulator.cycle().unwrap();
+        emulator.registers.set(Register::PC, 4);
+        emulator.cycle().unwrap();
+        assert!(emulator.halted());
+        assert_eq!(emulator.last_step(), emulator.time_step());
+    }
+}
+
+// Counts down from 5 and halts, running for long enough to fill a small history several times over.
+const COUNTDOWN: &str = "
+LOOP:
+LDAC ONE
+MVAC
+LDAC COUNT
+SUB
+STAC COUNT
+JMPZ DONE
+JMP LOOP
+DONE:
+HALT
+ONE: 1
+COUNT: 5
+";
+
+fn countdown(history: History, microprogrammed: bool) -> Emulator {
+    common::assemble(
+        COUNTDOWN,
+        Config {
+            history,
+            microprogrammed,
+            ..common::config()
+        },
+    )
+}
+
+fn words(emulator: &Emulator) -> Vec<Option<u32>> {
+    (0..MEMORY_SIZE as u32)
+        .map(|address| emulator.memory.get(address))
+        .collect()
+}
+
+/// Checks the machine is as a fresh one is after performing 'time_step' micro-operations.
+fn assert_replayed(emulator: &Emulator, time_step: usize, microprogrammed: bool) {
+    let mut fresh = countdown(History::default(), microprogrammed);
+    for _ in 0..time_step {
+        fresh.micro_step().unwrap();
+    }
+    assert_eq!(emulator.time_step(), time_step);
+    assert_eq!(
+        emulator.registers.values(),
+        fresh.registers.values(),
+        "at {time_step}"
+    );
+    assert_eq!(words(emulator), words(&fresh), "at {time_step}");
+    assert_eq!(emulator.micro_pc(), fresh.micro_pc(), "at {time_step}");
+}
+
+#[test]
+fn bounded_histories_step_back_to_their_first_step() {
+    let history = History {
+        limit: Some(20),
+        checkpoint_interval: 5,
+        ..Default::default()
+    };
+
+    for microprogrammed in [false, true] {
+        let mut emulator = countdown(history, microprogrammed);
+        common::run(&mut emulator).unwrap();
+        let halted = emulator.time_step();
+
+        // At least the limit is kept, older steps are discarded a checkpoint at a time.
+        let first_step = emulator.first_step();
+        assert!(first_step > 0);
+        assert!(halted - first_step >= 20);
+        assert!(halted - first_step < 20 + 5);
+        assert_eq!(first_step % 5, 0);
+
+        while emulator.step_backward() {}
+        assert_eq!(emulator.time_step(), first_step);
+        assert_replayed(&emulator, first_step, microprogrammed);
+
+        emulator.seek(halted);
+        assert_replayed(&emulator, halted, microprogrammed);
+        emulator.seek(0);
+        assert_eq!(emulator.time_step(), first_step);
+    }
+}
+
+#[test]
+fn seeking_matches_a_fresh_replay() {
+    let history = History {
+        checkpoint_interval: 7,
+        ..Default::default()
+    };
+
+    for microprogrammed in [false, true] {
+        let mut emulator = countdown(history, microprogrammed);
+        common::run(&mut emulator).unwrap();
+        let halted = emulator.time_step();
+
+        for time_step in [halted / 2, 3, halted - 1, 7, 0, 22, halted, 15, 1] {
+            emulator.seek(time_step);
+            assert_replayed(&emulator, time_step, microprogrammed);
+        }
+    }
+}
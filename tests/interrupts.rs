@@ -0,0 +1,94 @@
+//! The interrupt extension, in both control units.
+mod common;
+
+use common::run;
+use rsc::emulator::{config::Config, device::InputQueue, util::Register, Emulator};
+
+// Where the queue of input is attached, requesting an interrupt until it is empty.
+const QUEUE: u32 = 14;
+const HANDLER: u32 = 4;
+// Where the handler stores what it read.
+const STORED: u32 = 12;
+
+// Opcodes of the first instructions tried.
+const EI: u32 = 16;
+const DI: u32 = 17;
+
+// The first instruction, then INC twice and HALT. The handler at 4 stores a value read from the queue and returns.
+fn program(first: u32) -> Vec<u32> {
+    vec![first, 10, 10, 0, 1, QUEUE, 2, STORED, 18]
+}
+
+fn emulator(first: u32, input: &[u32], microprogrammed: bool) -> Emulator {
+    let mut emulator = Emulator::with_config(
+        &program(first),
+        Config {
+            interrupt_vector: Some(HANDLER),
+            microprogrammed,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    emulator
+        .memory
+        .attach(QUEUE, Box::new(InputQueue::new(input)));
+    emulator
+}
+
+#[test]
+fn interrupts_jump_to_the_vector_and_return() {
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(EI, &[9], microprogrammed);
+        emulator.cycle().unwrap();
+        assert_eq!(emulator.registers.get(Register::IE), 1);
+
+        // The interrupt is entered before the instruction following EI.
+        while emulator.registers.get(Register::PC) != HANDLER {
+            emulator.cycle().unwrap();
+            assert!(emulator.time_step() < 50, "the interrupt was never entered");
+        }
+        assert_eq!(emulator.registers.get(Register::EPC), 1);
+        assert_eq!(emulator.registers.get(Register::IE), 0);
+
+        // RETI returns to the saved address with interrupts enabled again.
+        while emulator.registers.get(Register::PC) != 1 {
+            emulator.cycle().unwrap();
+        }
+        assert_eq!(emulator.registers.get(Register::IE), 1);
+        assert_eq!(emulator.memory.get(STORED), Some(9));
+
+        run(&mut emulator).unwrap();
+        assert_eq!(emulator.registers.get(Register::ACC), 11);
+        assert_eq!(emulator.profile().get(HANDLER).executions, 1);
+    }
+}
+
+#[test]
+fn interrupts_are_masked_while_disabled() {
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(DI, &[9], microprogrammed);
+        run(&mut emulator).unwrap();
+        assert_eq!(emulator.registers.get(Register::ACC), 2);
+        assert_eq!(emulator.memory.get(STORED), Some(0));
+        assert_eq!(emulator.profile().get(HANDLER).executions, 0);
+    }
+}
+
+#[test]
+fn handlers_are_not_interrupted() {
+    // Both values are requested at once, the second is only taken after RETI enables interrupts again.
+    for microprogrammed in [false, true] {
+        let mut emulator = emulator(EI, &[9, 5], microprogrammed);
+        while !emulator.halted() {
+            emulator.cycle().unwrap();
+            let pc = emulator.registers.get(Register::PC);
+            if (HANDLER + 1..HANDLER + 5).contains(&pc) {
+                assert_eq!(emulator.registers.get(Register::EPC), 1, "at {pc:#x}");
+                assert_eq!(emulator.registers.get(Register::IE), 0, "at {pc:#x}");
+            }
+        }
+        assert_eq!(emulator.profile().get(HANDLER).executions, 2);
+        assert_eq!(emulator.memory.get(STORED), Some(5));
+        assert_eq!(emulator.registers.get(Register::ACC), 7);
+    }
+}
@@ -0,0 +1,227 @@
+//! The microprogrammed control unit against the hardwired one.
+mod common;
+
+use rsc::emulator::{
+    config::{Config, WordWidth},
+    device::InputQueue,
+    isa::{Instruction, Isa, Operation},
+    microcode::{Error, Microcode, Next},
+    util::{Fault, Register},
+    Assembler, Emulator,
+};
+
+const MEMORY_SIZE: u32 = 64;
+// Where a queue of input is attached, raising interrupts until it is read.
+const QUEUE: u32 = 60;
+// A bound on the steps compared, as interrupts and jumps back to the start can keep programs from halting.
+const MAX_STEPS: usize = 400;
+
+fn microprogrammed() -> Emulator {
+    Emulator::with_config(
+        &[0],
+        Config {
+            microprogrammed: true,
+            ..common::config()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn instruction_sets_are_mapped_again() {
+    let mut emulator = microprogrammed();
+    let isa = Isa::rsc()
+        .without("INC")
+        .with(Instruction::new("INC", 0x30, Operation::Inc));
+    emulator.set_isa(isa).unwrap();
+
+    let mapping = &emulator.microcode().unwrap().mapping;
+    assert!(mapping.contains_key(&0x30));
+    assert!(!mapping.contains_key(&10));
+}
+
+#[test]
+fn micro_routines_that_never_end_fault() {
+    // INC and HALT, with the micro-routine of INC jumping back to itself.
+    let mut emulator = Emulator::with_config(
+        &[10, 0],
+        Config {
+            microprogrammed: true,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    let mut microcode = emulator.microcode().unwrap().clone();
+    let inc = microcode
+        .rom
+        .iter()
+        .position(|instruction| instruction.name == "INC1")
+        .unwrap();
+    microcode.rom[inc].next = Next::Goto(inc);
+    assert!(emulator.set_microcode(Some(microcode)));
+
+    assert_eq!(emulator.cycle(), Err(Fault::MicroStepLimit(0)));
+    assert_eq!(emulator.time_step(), 0);
+    assert_eq!(emulator.registers.get(Register::PC), 0);
+    assert_eq!(emulator.registers.get(Register::ACC), 0);
+}
+
+#[test]
+fn instructions_without_a_micro_routine_are_refused() {
+    let mut emulator = microprogrammed();
+    let mut microcode = emulator.microcode().unwrap().clone();
+    microcode
+        .rom
+        .iter_mut()
+        .find(|instruction| instruction.name == "INC1")
+        .unwrap()
+        .name = "INCREMENT".to_string();
+    assert!(emulator.set_microcode(Some(microcode)));
+
+    assert_eq!(
+        emulator.set_isa(Isa::rsc()),
+        Err(Error::MissingRoutine("INC", "INC1"))
+    );
+    assert_eq!(emulator.isa().lookup("INC").map(|i| i.opcode), Some(10));
+}
+
+#[test]
+fn control_units_are_only_switched_between_instructions() {
+    // CLAC, INC, INC and HALT.
+    let mut emulator = Emulator::with_config(&[11, 10, 10, 0], common::config()).unwrap();
+    emulator.cycle().unwrap();
+    emulator.micro_step().unwrap();
+    let microcode = Microcode::for_isa(emulator.isa(), emulator.width());
+    assert!(!emulator.set_microcode(Some(microcode.clone())));
+    assert!(emulator.microcode().is_none());
+
+    while !emulator.at_instruction_boundary() {
+        emulator.micro_step().unwrap();
+    }
+    let time_step = emulator.time_step();
+    assert!(emulator.set_microcode(Some(microcode)));
+    assert_eq!(emulator.first_step(), time_step);
+    assert!(!emulator.step_backward());
+
+    while !emulator.halted() {
+        emulator.cycle().unwrap();
+    }
+    assert_eq!(emulator.registers.get(Register::ACC), 2);
+
+    // Stepping back goes no further than the switch, where the microprogrammed unit fetches the second INC.
+    while emulator.step_backward() {}
+    assert_eq!(emulator.time_step(), time_step);
+    assert_eq!(emulator.micro_pc(), Some(0));
+    assert_eq!(emulator.registers.get(Register::ACC), 1);
+}
+
+/// A program running 'line' with ACC loaded from B and R from A, then storing and printing ACC.
+fn source(line: &str, a: u32, b: u32) -> String {
+    format!(
+        "
+    LDAC A
+    MVAC
+    LDAC B
+    {line}
+    STAC C
+    OUT
+    HALT
+TARGET:
+    INC
+    HALT
+HANDLER:
+    LDAC B
+    RETI
+A: {a:X}
+B: {b:X}
+C: 0
+"
+    )
+}
+
+fn machine(assembler: &Assembler, width: WordWidth, microprogrammed: bool) -> Emulator {
+    let mut emulator = Emulator::with_config(
+        &assembler.instructions,
+        Config {
+            memory_size: MEMORY_SIZE as usize,
+            width,
+            interrupt_vector: Some(assembler.symbol_map["HANDLER"]),
+            extended_flags: true,
+            microprogrammed,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    emulator
+        .memory
+        .attach(QUEUE, Box::new(InputQueue::new(&[1, 2])));
+    emulator
+}
+
+fn words(emulator: &Emulator) -> Vec<Option<u32>> {
+    (0..MEMORY_SIZE)
+        .map(|address| emulator.memory.get(address))
+        .collect()
+}
+
+#[test]
+fn microcode_matches_the_hardwired_control_unit() {
+    for width in [WordWidth::Textbook, WordWidth::Wide] {
+        let mask = width.data_mask();
+        let high = mask / 2 + 1;
+        let values = [(3, 5), (0, 0), (mask, 1), (high, high), (1, mask)];
+
+        for instruction in Isa::rsc().iter() {
+            let line = match instruction.operation {
+                _ if !instruction.has_operand() => instruction.mnemonic.to_string(),
+                Operation::Load | Operation::Store => {
+                    format!("{} A", instruction.mnemonic)
+                }
+                _ => format!("{} TARGET", instruction.mnemonic),
+            };
+
+            for (a, b) in values {
+                let assembler = Assembler::parse_with(source(&line, a, b), 0, width, &Isa::rsc());
+                assert!(assembler.errors.is_none(), "{line}");
+                let mut hardwired = machine(&assembler, width, false);
+                let mut microprogrammed = machine(&assembler, width, true);
+
+                for _ in 0..MAX_STEPS {
+                    let context = format!("{line} with {a:#x}, {b:#x} in {}", width.as_str());
+                    let signals = microprogrammed
+                        .micro_instruction()
+                        .unwrap()
+                        .control_signals(width, true);
+                    let expected = hardwired.micro_step().map(|_| ());
+                    assert_eq!(
+                        microprogrammed.micro_step().map(|_| ()),
+                        expected,
+                        "{context}"
+                    );
+                    assert_eq!(
+                        microprogrammed.registers.values(),
+                        hardwired.registers.values(),
+                        "{context} at step {}",
+                        hardwired.time_step()
+                    );
+                    assert_eq!(words(&microprogrammed), words(&hardwired), "{context}");
+                    assert_eq!(microprogrammed.time_step(), hardwired.time_step());
+                    assert_eq!(microprogrammed.cycles(), hardwired.cycles());
+                    if expected.is_ok() {
+                        let datapath = microprogrammed.datapath();
+                        assert_eq!(datapath, hardwired.datapath(), "{context}");
+                        assert_eq!(datapath.signals().collect::<Vec<_>>(), signals, "{context}");
+                    }
+                    assert_eq!(
+                        microprogrammed.at_instruction_boundary(),
+                        hardwired.at_instruction_boundary()
+                    );
+                    if hardwired.halted() || expected.is_err() {
+                        break;
+                    }
+                }
+                assert_eq!(microprogrammed.output(), hardwired.output());
+            }
+        }
+    }
+}
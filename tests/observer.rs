@@ -0,0 +1,159 @@
+//! Observers told about what the emulator does.
+mod common;
+
+use common::run;
+use rsc::emulator::{
+    isa::Instruction,
+    observer::Observer,
+    util::{Fault, Register},
+    Emulator,
+};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Fetch(u32, &'static str),
+    RegisterSet(Register, u32),
+    MemoryRead(u32, u32),
+    MemoryWrite(u32, u32),
+    Output(u32),
+    Halt,
+}
+
+/// Records every event into a log shared with the test.
+struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+impl Observer for Recorder {
+    fn fetch(&mut self, address: u32, instruction: Instruction) {
+        self.0
+            .borrow_mut()
+            .push(Event::Fetch(address, instruction.mnemonic));
+    }
+
+    fn register_set(&mut self, reg: Register, val: u32) {
+        self.0.borrow_mut().push(Event::RegisterSet(reg, val));
+    }
+
+    fn memory_read(&mut self, address: u32, val: u32) {
+        self.0.borrow_mut().push(Event::MemoryRead(address, val));
+    }
+
+    fn memory_write(&mut self, address: u32, val: u32) {
+        self.0.borrow_mut().push(Event::MemoryWrite(address, val));
+    }
+
+    fn output(&mut self, val: u32) {
+        self.0.borrow_mut().push(Event::Output(val));
+    }
+
+    fn halt(&mut self) {
+        self.0.borrow_mut().push(Event::Halt);
+    }
+}
+
+fn observed(program: &[u32], microprogrammed: bool) -> (Emulator, Rc<RefCell<Vec<Event>>>) {
+    let mut emulator = Emulator::with_config(
+        program,
+        rsc::emulator::config::Config {
+            microprogrammed,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    emulator.observe(Box::new(Recorder(log.clone())));
+    (emulator, log)
+}
+
+#[test]
+fn observers_are_told_about_every_instruction() {
+    // LDAC 7, OUT, STAC 8 and HALT, with 5 at 7.
+    for microprogrammed in [false, true] {
+        let (mut emulator, log) = observed(&[1, 7, 7, 2, 8, 0, 0, 5, 0], microprogrammed);
+        run(&mut emulator).unwrap();
+        let log = log.borrow();
+
+        let fetches: Vec<Event> = log
+            .iter()
+            .copied()
+            .filter(|event| matches!(event, Event::Fetch(..)))
+            .collect();
+        assert_eq!(
+            fetches,
+            vec![
+                Event::Fetch(0, "LDAC"),
+                Event::Fetch(2, "OUT"),
+                Event::Fetch(3, "STAC"),
+                Event::Fetch(5, "HALT"),
+            ]
+        );
+        assert!(log.contains(&Event::MemoryRead(7, 5)));
+        assert!(log.contains(&Event::RegisterSet(Register::ACC, 5)));
+        let rest: Vec<Event> = log
+            .iter()
+            .copied()
+            .filter(|event| {
+                matches!(
+                    event,
+                    Event::MemoryWrite(..) | Event::Output(_) | Event::Halt
+                )
+            })
+            .collect();
+        assert_eq!(
+            rest,
+            vec![Event::Output(5), Event::MemoryWrite(8, 5), Event::Halt]
+        );
+    }
+}
+
+#[test]
+fn observers_are_told_as_each_micro_operation_completes() {
+    let (mut emulator, log) = observed(&[10, 0], false);
+
+    // FETCH1 only moves PC into AR.
+    emulator.micro_step().unwrap();
+    assert_eq!(*log.borrow(), vec![Event::RegisterSet(Register::AR, 0)]);
+
+    while !emulator.at_instruction_boundary() {
+        emulator.micro_step().unwrap();
+    }
+    let told = log.borrow().len();
+    assert!(log.borrow().contains(&Event::Fetch(0, "INC")));
+    assert_eq!(
+        log.borrow().last(),
+        Some(&Event::RegisterSet(Register::Z, 0))
+    );
+
+    // Nothing is told when moving through the timeline.
+    assert!(emulator.step_backward());
+    emulator.seek(0);
+    emulator.seek(emulator.last_step());
+    assert_eq!(log.borrow().len(), told);
+}
+
+#[test]
+fn faulting_cycles_are_not_told() {
+    // INC, then a STAC past the end of the address space.
+    for microprogrammed in [false, true] {
+        let (mut emulator, log) = observed(&[10, 2, 0xff, 0], microprogrammed);
+        emulator.cycle().unwrap();
+        let told = log.borrow().clone();
+
+        assert_eq!(emulator.cycle(), Err(Fault::AddressOutOfRange(1, 0xff)));
+        assert_eq!(*log.borrow(), told);
+    }
+}
+
+#[test]
+fn removed_observers_are_not_told() {
+    let (mut emulator, log) = observed(&[10, 10, 0], false);
+    let recorder = Rc::new(RefCell::new(Vec::new()));
+    let id = emulator.observe(Box::new(Recorder(recorder.clone())));
+    emulator.cycle().unwrap();
+    assert_eq!(*recorder.borrow(), *log.borrow());
+
+    assert!(emulator.unobserve(id).is_some());
+    assert!(emulator.unobserve(id).is_none());
+    emulator.cycle().unwrap();
+    assert!(log.borrow().len() > recorder.borrow().len());
+}
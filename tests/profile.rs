@@ -0,0 +1,97 @@
+//! Counting how often each address is executed, read and written.
+mod common;
+
+use common::run;
+use rsc::emulator::{
+    config::{Config, History},
+    profile::Counts,
+    Assembler, Emulator,
+};
+
+// Counts down from 3 and halts.
+const COUNTDOWN: &str = "
+LOOP:
+LDAC ONE
+MVAC
+LDAC COUNT
+SUB
+STAC COUNT
+JMPZ DONE
+JMP LOOP
+DONE:
+HALT
+ONE: 1
+COUNT: 3
+";
+
+fn countdown(microprogrammed: bool) -> (Emulator, Assembler) {
+    let config = Config {
+        history: History {
+            checkpoint_interval: 16,
+            ..Default::default()
+        },
+        microprogrammed,
+        ..common::config()
+    };
+    let assembler = Assembler::parse_for(COUNTDOWN.to_string(), config.base, config.width);
+    (common::assemble(COUNTDOWN, config), assembler)
+}
+
+fn counts(emulator: &Emulator) -> Vec<(u32, Counts)> {
+    emulator.profile().iter().collect()
+}
+
+#[test]
+fn executions_are_counted_per_address() {
+    for microprogrammed in [false, true] {
+        let (mut emulator, assembler) = countdown(microprogrammed);
+        run(&mut emulator).unwrap();
+        let profile = emulator.profile();
+        let labels = &assembler.symbol_map;
+
+        // Six instructions each time round the loop, JMP on all but the last, then HALT.
+        assert_eq!(profile.instructions(), 3 * 6 + 2 + 1);
+        assert_eq!(profile.get(labels["LOOP"]).executions, 3);
+
+        let jmpz = labels["DONE"] - 4;
+        let counts = profile.get(jmpz);
+        assert_eq!(
+            (counts.executions, counts.taken, counts.not_taken),
+            (3, 1, 2)
+        );
+        assert_eq!(profile.get(labels["DONE"] - 2).executions, 2);
+        assert_eq!(profile.get(labels["DONE"]).executions, 1);
+
+        assert_eq!(profile.get(labels["ONE"]).reads, 3);
+        assert_eq!(profile.get(labels["ONE"]).writes, 0);
+        assert_eq!(profile.get(labels["COUNT"]).reads, 3);
+        assert_eq!(profile.get(labels["COUNT"]).writes, 3);
+        assert!(profile.get(labels["COUNT"] + 1).untouched());
+
+        let hottest = profile.hot_spots()[0];
+        assert_eq!(hottest, (labels["LOOP"], 3));
+    }
+}
+
+#[test]
+fn stepping_back_takes_back_what_was_counted() {
+    for microprogrammed in [false, true] {
+        let (mut emulator, _) = countdown(microprogrammed);
+        let mut profiles = vec![counts(&emulator)];
+        while !emulator.halted() {
+            emulator.micro_step().unwrap();
+            profiles.push(counts(&emulator));
+        }
+
+        while emulator.step_backward() {
+            assert_eq!(counts(&emulator), profiles[emulator.time_step()]);
+        }
+        assert_eq!(emulator.time_step(), 0);
+        assert!(counts(&emulator).is_empty());
+
+        for time_step in [profiles.len() - 1, 5, 40, 17, 0, 33] {
+            emulator.seek(time_step);
+            assert_eq!(counts(&emulator), profiles[time_step], "at {time_step}");
+        }
+    }
+}
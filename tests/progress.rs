@@ -0,0 +1,57 @@
+//! Stopping programs that are looping forever or have run out of budget.
+mod common;
+
+use rsc::{
+    debugger::{progress::Stop, Debugger},
+    emulator::{config::Config, device::Timer},
+};
+
+// Polls the word at 4, or the device attached there, with LDAC 4 and JMP 0 for ever.
+const POLL: [u32; 5] = [1, 4, 5, 0, 0];
+
+// INC and JMP 0 for ever.
+const COUNT_UP: [u32; 3] = [10, 5, 0];
+
+#[test]
+fn busy_waits_make_no_progress() {
+    for microprogrammed in [false, true] {
+        let config = Config {
+            microprogrammed,
+            ..common::config()
+        };
+        let mut debugger = Debugger::with_config(&POLL, config).unwrap();
+        debugger.stepi(100);
+        assert_eq!(debugger.stop(), Some(Stop::NoProgress(0, 2)));
+        assert!(debugger.emulator.profile().instructions() < 10);
+    }
+}
+
+#[test]
+fn polling_a_timer_keeps_running() {
+    let mut debugger = Debugger::with_config(&POLL[..4], common::config()).unwrap();
+    debugger.emulator.memory.attach(4, Box::new(Timer::new()));
+    debugger.stepi(500);
+    assert_eq!(debugger.stop(), None);
+    assert_eq!(debugger.emulator.profile().instructions(), 500);
+}
+
+#[test]
+fn counting_up_keeps_running() {
+    let mut debugger = Debugger::new(&COUNT_UP).unwrap();
+    debugger.stepi(500);
+    assert_eq!(debugger.stop(), None);
+}
+
+#[test]
+fn budgets_stop_after_exactly_their_instructions() {
+    let mut debugger = Debugger::new(&COUNT_UP).unwrap();
+    debugger.instruction_budget = Some(7);
+    debugger.stepi(100);
+    assert_eq!(debugger.stop(), Some(Stop::BudgetExhausted(7)));
+    assert_eq!(debugger.emulator.profile().instructions(), 7);
+
+    // Stepping back below the budget lets it run up to it again.
+    debugger.backi(3);
+    debugger.stepi(100);
+    assert_eq!(debugger.emulator.profile().instructions(), 7);
+}
@@ -0,0 +1,66 @@
+//! Finding the writes to registers and memory in the recorded history.
+mod common;
+
+use rsc::{
+    debugger::{
+        provenance::{Location, Write},
+        Debugger,
+    },
+    emulator::util::Register,
+};
+
+// LDAC 8, STAC 9, INC, STAC 9 and HALT for the wide machine, with 7 at 8.
+const PROGRAM: [u32; 10] = [1, 8, 2, 9, 10, 2, 9, 0, 7, 0];
+
+fn debugger() -> Debugger {
+    let mut debugger = Debugger::with_config(&PROGRAM, common::config()).unwrap();
+    debugger.stepi(10);
+    assert!(debugger.halted());
+    debugger
+}
+
+fn mnemonic(write: &Write) -> Option<&'static str> {
+    write.instruction.map(|instruction| instruction.mnemonic)
+}
+
+#[test]
+fn last_writes_are_found() {
+    let debugger = debugger();
+
+    let write = debugger.last_write(Location::Address(9)).unwrap();
+    assert_eq!((write.pc, mnemonic(&write)), (5, Some("STAC")));
+    assert_eq!((write.previous, write.value), (7, 8));
+
+    let write = debugger
+        .last_write(Location::Register(Register::ACC))
+        .unwrap();
+    assert_eq!((write.pc, mnemonic(&write)), (4, Some("INC")));
+    assert_eq!((write.previous, write.value), (7, 8));
+
+    let pcs: Vec<u32> = debugger
+        .writes(Location::Address(9))
+        .map(|write| write.pc)
+        .collect();
+    assert_eq!(pcs, vec![5, 2]);
+
+    assert_eq!(debugger.last_write(Location::Address(15)), None);
+}
+
+#[test]
+fn jumping_to_the_last_write_lands_just_before_it() {
+    let mut debugger = debugger();
+    let write = debugger.last_write(Location::Address(9)).unwrap();
+
+    assert!(debugger.jump_to_last_write(Location::Address(9)));
+    assert_eq!(debugger.emulator.time_step(), write.time_step);
+    assert_eq!(debugger.emulator.memory.get(9), Some(7));
+
+    // From there the write before it is the most recent.
+    assert!(debugger.jump_to_last_write(Location::Address(9)));
+    assert_eq!(debugger.emulator.memory.get(9), Some(0));
+    assert_eq!(debugger.last_write(Location::Address(9)), None);
+
+    let time_step = debugger.emulator.time_step();
+    assert!(!debugger.jump_to_last_write(Location::Address(9)));
+    assert_eq!(debugger.emulator.time_step(), time_step);
+}
@@ -0,0 +1,135 @@
+//! Returning the machine to its power-on state.
+mod common;
+
+use common::run;
+use rsc::emulator::{
+    config::Guard,
+    config::{Config, Reset},
+    device::InputQueue,
+    memory::LoadError,
+    util::{Fault, Register},
+    Emulator,
+};
+
+// LDAC from the input queue and HALT, for the wide machine.
+const PROGRAM: [u32; 3] = [1, QUEUE, 0];
+const QUEUE: u32 = 8;
+
+fn remaining(emulator: &Emulator) -> u32 {
+    let (_, queue) = emulator.memory.devices().next().unwrap();
+    queue.peek(1)
+}
+
+#[test]
+fn devices_power_on_again() {
+    let mut emulator = Emulator::with_config(&PROGRAM, common::config()).unwrap();
+    emulator
+        .memory
+        .attach(QUEUE, Box::new(InputQueue::new(&[5, 6])));
+
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 5);
+    assert_eq!(remaining(&emulator), 1);
+
+    // Loading a program keeps what the devices hold, resetting does not.
+    emulator.load_program(&PROGRAM, 0, false).unwrap();
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 6);
+    assert_eq!(remaining(&emulator), 0);
+
+    emulator.reset();
+    assert_eq!(remaining(&emulator), 2);
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 5);
+}
+
+#[test]
+fn z_matches_randomized_acc() {
+    for seed in 0..32 {
+        let reset = Reset {
+            randomize: Some(seed),
+            ..Default::default()
+        };
+        let values = reset.values(0);
+        let acc = values[Register::ACC as usize];
+        assert_eq!(values[Register::Z as usize], (acc == 0) as u32);
+
+        let values = reset.with_register(Register::ACC, 0).values(0);
+        assert_eq!(values[Register::Z as usize], 1);
+        let values = reset.with_register(Register::Z, 0).values(0);
+        assert_eq!(values[Register::Z as usize], 0);
+    }
+}
+
+#[test]
+fn programs_are_loaded_over_kept_memory() {
+    // STAC 12 and HALT, then LDAC 12 and HALT at another base, reading what the first left behind.
+    let mut emulator = Emulator::with_config(
+        &[2, 12, 0],
+        Config {
+            reset: Reset::default().with_register(Register::ACC, 7),
+            uninitialized_guard: Guard::Stop,
+            ..common::config()
+        },
+    )
+    .unwrap();
+    run(&mut emulator).unwrap();
+
+    emulator.set_reset_config(Reset::default());
+    emulator.load_program(&[1, 12, 0], 4, true).unwrap();
+    assert_eq!(emulator.registers.get(Register::PC), 4);
+    assert_eq!(emulator.registers.get(Register::ACC), 0);
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 7);
+    assert_eq!(emulator.memory.get(0), Some(2));
+
+    // Without keeping memory, the word is zero and was never written.
+    emulator.load_program(&[1, 12, 0], 4, false).unwrap();
+    assert_eq!(emulator.memory.get(0), Some(0));
+    assert_eq!(emulator.cycle(), Err(Fault::UninitializedRead(4, 12)));
+}
+
+#[test]
+fn kept_code_can_be_called() {
+    // INC and HALT, then JMP back into it from another base.
+    let mut emulator = Emulator::with_config(&[10, 0], common::config()).unwrap();
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 1);
+
+    emulator.load_program(&[5, 0], 8, true).unwrap();
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 1);
+    assert_eq!(emulator.registers.get(Register::PC), 2);
+
+    // Words that were never loaded still cannot be executed.
+    emulator.load_program(&[5, 0], 8, false).unwrap();
+    assert_eq!(run(&mut emulator), Err(Fault::FetchOutOfProgram(0)));
+}
+
+#[test]
+fn programs_that_do_not_fit_are_refused() {
+    let config = Config {
+        base: 14,
+        ..common::config()
+    };
+    assert!(matches!(
+        Emulator::with_config(&PROGRAM, config),
+        Err(LoadError::DoesNotFit(3, 14, 16))
+    ));
+
+    let mut emulator = Emulator::with_config(&PROGRAM, common::config()).unwrap();
+    emulator
+        .memory
+        .attach(QUEUE, Box::new(InputQueue::new(&[5])));
+
+    assert_eq!(
+        emulator.load_program(&PROGRAM, 14, false),
+        Err(LoadError::DoesNotFit(3, 14, 16))
+    );
+    assert_eq!(
+        emulator.load_program(&PROGRAM, 7, false),
+        Err(LoadError::OverlapsDevice(3, 7))
+    );
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::ACC), 5);
+}
@@ -0,0 +1,199 @@
+//! Saving a debugging session and restoring it again.
+use rsc::{
+    debugger::{
+        snapshot::{Error, SNAPSHOT_VERSION},
+        Debugger,
+    },
+    emulator::{
+        config::{Config, WordWidth},
+        isa::{Instruction, Isa, Operation},
+        util::Register,
+        Assembler,
+    },
+};
+
+const SOURCE: &str = include_str!("selection_sort.txt");
+
+fn config() -> Config {
+    Config {
+        memory_size: 1024,
+        ..Default::default()
+    }
+}
+
+fn registers(debugger: &Debugger) -> Vec<u32> {
+    Register::iter()
+        .map(|reg| debugger.emulator.registers.get(*reg))
+        .collect()
+}
+
+fn words(debugger: &Debugger) -> Vec<u32> {
+    (0..1024)
+        .map(|address| debugger.emulator.memory.get(address).unwrap())
+        .collect()
+}
+
+/// Runs to the end of the program, giving what it printed.
+fn run_to_halt(debugger: &mut Debugger) -> Vec<u32> {
+    while !debugger.halted() && !debugger.stopped() {
+        debugger.step_over();
+    }
+    debugger
+        .emulator
+        .output()
+        .iter()
+        .map(|output| output.value)
+        .collect()
+}
+
+#[test]
+fn round_trip_restores_state_and_history() {
+    let assembler = Assembler::parse(SOURCE.to_string());
+    let mut debugger = Debugger::with_config(&assembler.instructions, config()).unwrap();
+    debugger.stepi(200);
+    debugger.micro_stepi(2);
+
+    let (mut loaded, source) = Debugger::load(&debugger.save(SOURCE).unwrap()).unwrap();
+    assert_eq!(source, SOURCE);
+    assert_eq!(loaded.emulator.time_step(), debugger.emulator.time_step());
+    assert_eq!(loaded.emulator.first_step(), debugger.emulator.first_step());
+    assert_eq!(registers(&loaded), registers(&debugger));
+    assert_eq!(words(&loaded), words(&debugger));
+
+    debugger.backi(50);
+    loaded.backi(50);
+    assert_eq!(registers(&loaded), registers(&debugger));
+    assert_eq!(words(&loaded), words(&debugger));
+
+    assert_eq!(run_to_halt(&mut loaded), run_to_halt(&mut debugger));
+    assert_eq!(registers(&loaded), registers(&debugger));
+}
+
+#[test]
+fn round_trip_keeps_layout_and_instruction_set() {
+    let isa = Isa::rsc()
+        .without("INC")
+        .with(Instruction::new("INC", 0x30, Operation::Inc));
+    let source = "CLAC\nINC\nINC\nOUT\nHALT";
+    let assembler = Assembler::parse_with(source.to_string(), 0x20, WordWidth::Wide, &isa);
+    let mut debugger = Debugger::with_config(
+        &assembler.instructions,
+        Config {
+            base: 0x20,
+            width: WordWidth::Wide,
+            ..config()
+        },
+    )
+    .unwrap();
+    debugger.emulator.set_isa(isa.clone()).unwrap();
+    debugger.stepi(2);
+    let data = debugger.save(source).unwrap();
+
+    assert!(matches!(
+        Debugger::load(&data),
+        Err(Error::UnknownInstruction(mnemonic, 0x30)) if mnemonic == "INC"
+    ));
+
+    let (mut loaded, source) = Debugger::load_with(&data, &isa).unwrap();
+    let emulator = &loaded.emulator;
+    let reassembled = Assembler::parse_with(
+        source,
+        emulator.memory.base(),
+        emulator.width(),
+        emulator.isa(),
+    );
+    assert_eq!(reassembled.instructions, assembler.instructions);
+    assert_eq!(run_to_halt(&mut loaded), vec![2]);
+}
+
+#[test]
+fn inconsistent_snapshots_are_rejected() {
+    let assembler = Assembler::parse(SOURCE.to_string());
+    let mut debugger = Debugger::with_config(&assembler.instructions, config()).unwrap();
+    debugger.stepi(20);
+    let data = debugger.save(SOURCE).unwrap();
+
+    let tamper = |edit: fn(&mut serde_json::Value)| {
+        let mut value: serde_json::Value = serde_json::from_str(&data).unwrap();
+        edit(&mut value["debugger"]["emulator"]);
+        Debugger::load(&value.to_string()).err()
+    };
+
+    assert!(matches!(
+        tamper(|emulator| emulator["trace"] = serde_json::json!([])),
+        Some(Error::Inconsistent("trace"))
+    ));
+    assert!(matches!(
+        tamper(|emulator| emulator["time_step"] = serde_json::json!(5)),
+        Some(Error::Inconsistent(_))
+    ));
+    assert!(matches!(
+        tamper(|emulator| emulator["memory"]["first_writes"] = serde_json::json!([])),
+        Some(Error::Inconsistent("memory"))
+    ));
+    assert!(matches!(
+        tamper(|emulator| emulator["registers"]["engine"]["changes"][3] =
+            serde_json::json!([[99, 0, 0]])),
+        Some(Error::Inconsistent("registers"))
+    ));
+    assert!(matches!(
+        tamper(|emulator| emulator["checkpoints"][0]["memory"]["words"] = serde_json::json!([0])),
+        Some(Error::Inconsistent("checkpoints"))
+    ));
+    assert!(matches!(
+        tamper(|emulator| emulator["program"] = serde_json::json!(vec![0; 2048])),
+        Some(Error::Inconsistent("program"))
+    ));
+}
+
+#[test]
+fn values_wider_than_the_machine_are_rejected() {
+    let config = Config {
+        width: WordWidth::Textbook,
+        ..config()
+    };
+    let debugger = Debugger::with_config(&[0], config).unwrap();
+    let data = debugger.save("").unwrap();
+
+    let tamper = |edit: &dyn Fn(&mut serde_json::Value)| {
+        let mut value: serde_json::Value = serde_json::from_str(&data).unwrap();
+        edit(&mut value["debugger"]["emulator"]);
+        Debugger::load(&value.to_string()).err()
+    };
+
+    assert!(tamper(&|_| ()).is_none());
+    assert!(matches!(
+        tamper(
+            &|emulator| emulator["registers"]["registers"][Register::ACC as usize] =
+                serde_json::json!(0x100)
+        ),
+        Some(Error::Inconsistent("registers"))
+    ));
+    assert!(matches!(
+        tamper(
+            &|emulator| emulator["registers"]["registers"][Register::PC as usize] =
+                serde_json::json!(0x10000)
+        ),
+        Some(Error::Inconsistent("registers"))
+    ));
+    assert!(matches!(
+        tamper(&|emulator| emulator["memory"]["underlying"][3] = serde_json::json!(0x1FF)),
+        Some(Error::Inconsistent("memory"))
+    ));
+    assert!(matches!(
+        tamper(&|emulator| emulator["memory"]["width"] = serde_json::json!("Wide")),
+        Some(Error::Inconsistent("memory"))
+    ));
+}
+
+#[test]
+fn other_versions_are_reported() {
+    let debugger = Debugger::new(&[0]).unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&debugger.save("").unwrap()).unwrap();
+    value["version"] = serde_json::json!(SNAPSHOT_VERSION + 1);
+
+    assert!(matches!(
+        Debugger::load(&value.to_string()),
+        Err(Error::UnsupportedVersion(version)) if version == SNAPSHOT_VERSION + 1
+    ));
+}
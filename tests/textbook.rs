@@ -0,0 +1,92 @@
+//! The textbook machine, with 8-bit data and 16-bit addresses taking two words.
+mod common;
+
+use common::run;
+use rsc::emulator::{
+    config::{Config, WordWidth},
+    util::{Error, Register},
+    Assembler, Emulator,
+};
+use std::collections::HashMap;
+
+// Where programs are loaded, high enough that their addresses need both bytes.
+const BASE: u32 = 0x1E0;
+
+fn config() -> Config {
+    Config {
+        memory_size: 0x200,
+        base: BASE,
+        width: WordWidth::Textbook,
+        ..Default::default()
+    }
+}
+
+/// Assembles the source for the textbook machine at the base, giving the address of each label as well.
+fn assemble(source: &str) -> (Emulator, HashMap<String, u32>) {
+    let assembler = Assembler::parse_for(source.to_string(), BASE, WordWidth::Textbook);
+    assert!(assembler.errors.is_none(), "{:?}", assembler.errors);
+    let emulator = Emulator::with_config(&assembler.instructions, config()).unwrap();
+    (emulator, assembler.symbol_map)
+}
+
+#[test]
+fn operands_are_two_little_endian_words() {
+    let (mut emulator, labels) = assemble("JMP TARGET\nHALT\nTARGET:\nLDAC VALUE\nHALT\nVALUE: 2A");
+    assert_eq!(labels["TARGET"], 0x1E4);
+    assert_eq!(emulator.memory.get(BASE + 1), Some(0xE4));
+    assert_eq!(emulator.memory.get(BASE + 2), Some(0x01));
+
+    emulator.cycle().unwrap();
+    assert_eq!(emulator.registers.get(Register::PC), 0x1E4);
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.registers.get(Register::AR), labels["VALUE"]);
+    assert_eq!(emulator.registers.get(Register::ACC), 0x2A);
+}
+
+#[test]
+fn data_wraps_around_at_eight_bits() {
+    // Stores FF + 1, then FF + 2.
+    let (mut emulator, labels) = assemble(
+        "LDAC A\nINC\nSTAC C\nLDAC B\nMVAC\nLDAC A\nADD\nSTAC D\nHALT\nA: FF\nB: 2\nC: 7\nD: 0",
+    );
+    run(&mut emulator).unwrap();
+    assert_eq!(emulator.memory.get(labels["C"]), Some(0));
+    assert_eq!(emulator.memory.get(labels["D"]), Some(1));
+    assert_eq!(emulator.registers.get(Register::ACC), 1);
+}
+
+#[test]
+fn loaded_words_are_truncated_to_eight_bits() {
+    let emulator = Emulator::with_config(&[0, 0x1FF, 0x100], config()).unwrap();
+    assert_eq!(emulator.memory.get(BASE + 1), Some(0xFF));
+    assert_eq!(emulator.memory.get(BASE + 2), Some(0));
+}
+
+#[test]
+fn registers_are_masked_to_their_width() {
+    let mut emulator = Emulator::with_config(&[0], config()).unwrap();
+    for (reg, written, held) in [
+        (Register::PC, 0x1_2345, 0x2345),
+        (Register::AR, 0xABCD_EF01, 0xEF01),
+        (Register::ACC, 0x1FF, 0xFF),
+        (Register::R, 0x180, 0x80),
+        (Register::Z, 2, 0),
+    ] {
+        emulator.registers.set(reg, written);
+        assert_eq!(emulator.registers.get(reg), held, "{reg:?}");
+    }
+}
+
+#[test]
+fn initializers_must_fit_a_word() {
+    let assembler = Assembler::parse_for("X: 100".to_string(), 0, WordWidth::Textbook);
+    assert!(matches!(
+        assembler.errors.as_deref(),
+        Some([Error::InitializerOutOfRange(name, _)]) if name == "X"
+    ));
+
+    let assembler = Assembler::parse_for("X: FF".to_string(), 0, WordWidth::Textbook);
+    assert!(assembler.errors.is_none());
+    let assembler = Assembler::parse_for("X: 100".to_string(), 0, WordWidth::Wide);
+    assert!(assembler.errors.is_none());
+}
@@ -0,0 +1,93 @@
+//! The number of micro-operations each instruction takes, in both control units.
+mod common;
+
+use rsc::emulator::{
+    config::{Config, Reset, WordWidth},
+    isa::Isa,
+    util::Register,
+};
+
+// FETCH1 to FETCH3, before every instruction.
+const FETCH: usize = 3;
+
+/// The micro-operations after the fetch of each instruction of the textbook and wide machines, with every flag clear
+/// and with every flag set. Only the conditional jumps differ, taking JMPZN1 and JMPZN2 or the states of JMP.
+const EXECUTE: [(&str, [usize; 2], [usize; 2]); 22] = [
+    ("HALT", [1, 1], [1, 1]),
+    ("LDAC", [5, 5], [4, 4]),
+    ("STAC", [5, 5], [4, 4]),
+    ("MVAC", [1, 1], [1, 1]),
+    ("MOVR", [1, 1], [1, 1]),
+    ("JMP", [3, 3], [2, 2]),
+    ("JMPZ", [2, 3], [1, 2]),
+    ("OUT", [1, 1], [1, 1]),
+    ("SUB", [1, 1], [1, 1]),
+    ("ADD", [1, 1], [1, 1]),
+    ("INC", [1, 1], [1, 1]),
+    ("CLAC", [1, 1], [1, 1]),
+    ("AND", [1, 1], [1, 1]),
+    ("OR", [1, 1], [1, 1]),
+    ("ASHR", [1, 1], [1, 1]),
+    ("NOT", [1, 1], [1, 1]),
+    ("EI", [1, 1], [1, 1]),
+    ("DI", [1, 1], [1, 1]),
+    ("RETI", [1, 1], [1, 1]),
+    ("JMPC", [2, 3], [1, 2]),
+    ("JMPV", [2, 3], [1, 2]),
+    ("JMPN", [2, 3], [1, 2]),
+];
+
+#[test]
+fn every_instruction_is_timed() {
+    let isa = Isa::rsc();
+    assert_eq!(isa.iter().count(), EXECUTE.len());
+    for (mnemonic, _, _) in EXECUTE {
+        assert!(isa.lookup(mnemonic).is_some(), "{mnemonic}");
+    }
+}
+
+#[test]
+fn instructions_take_their_micro_operations() {
+    let isa = Isa::rsc();
+
+    for (mnemonic, textbook, wide) in EXECUTE {
+        let instruction = isa.lookup(mnemonic).unwrap();
+        let line = match instruction.has_operand() {
+            true => format!("{mnemonic} TARGET"),
+            false => mnemonic.to_string(),
+        };
+        let source = format!("{line}\nHALT\nTARGET: 0\n");
+
+        for (width, steps) in [(WordWidth::Textbook, textbook), (WordWidth::Wide, wide)] {
+            for (flag, steps) in [0, 1].into_iter().zip(steps) {
+                let reset = [Register::Z, Register::C, Register::V, Register::N]
+                    .into_iter()
+                    .fold(Reset::default(), |reset, reg| {
+                        reset.with_register(reg, flag)
+                    });
+
+                for microprogrammed in [false, true] {
+                    let mut emulator = common::assemble(
+                        &source,
+                        Config {
+                            width,
+                            extended_flags: true,
+                            interrupt_vector: Some(0),
+                            microprogrammed,
+                            reset,
+                            ..common::config()
+                        },
+                    );
+                    let context = format!(
+                        "{mnemonic} with flags {flag} in {}, microprogrammed {microprogrammed}",
+                        width.as_str()
+                    );
+
+                    emulator.cycle().unwrap();
+                    assert_eq!(emulator.time_step(), FETCH + steps, "{context}");
+                    assert_eq!(emulator.cycles(), (FETCH + steps) as u64, "{context}");
+                }
+            }
+        }
+    }
+}